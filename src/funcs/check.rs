@@ -0,0 +1,179 @@
+use {
+  crate::{
+    funcs::{
+      pkgs::count_updates,
+      system_info::{disk_percent, memory_percent},
+      thresholds::{level, Level},
+    },
+    util::statics::CONF,
+  },
+  std::process::Stdio,
+  tokio::process::Command,
+};
+
+/// A single value measured by `draconis check`, reported as Nagios perfdata.
+struct Metric {
+  name: &'static str,
+  value: Option<f64>,
+  unit: &'static str,
+  warn: Option<f64>,
+  crit: Option<f64>,
+  max: Option<f64>,
+  message: String,
+}
+
+impl Metric {
+  fn perfdata(&self) -> Option<String> {
+    let threshold = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+    Some(format!(
+      "{}={}{};{};{};0;{}",
+      self.name,
+      self.value?,
+      self.unit,
+      threshold(self.warn),
+      threshold(self.crit),
+      threshold(self.max)
+    ))
+  }
+}
+
+async fn count_failed_services() -> Option<f64> {
+  let output = Command::new("systemctl")
+    .args(["list-units", "--state=failed", "--plain", "--no-legend"])
+    .stderr(Stdio::null())
+    .output()
+    .await
+    .ok()?;
+
+  if !output.status.success() {
+    return None;
+  }
+
+  Some(
+    String::from_utf8_lossy(&output.stdout)
+      .lines()
+      .filter(|line| !line.trim().is_empty())
+      .count() as f64,
+  )
+}
+
+/// Evaluates every metric that has a threshold configured, prints a Nagios-style
+/// status line and returns the matching exit code.
+#[tracing::instrument]
+pub(crate) async fn run_check() -> i32 {
+  let mut metrics = Vec::new();
+
+  let (warn, crit) = (
+    CONF.system.mem_usage.warn_percent,
+    CONF.system.mem_usage.crit_percent,
+  );
+  if warn.is_some() || crit.is_some() {
+    let value = memory_percent();
+    metrics.push(Metric {
+      name: "memory",
+      value,
+      unit: "%",
+      warn,
+      crit,
+      max: Some(100.0),
+      message: format!("memory at {:.0}%", value.unwrap_or_default()),
+    });
+  }
+
+  let (warn, crit) = (
+    CONF.system.disk_usage.warn_percent,
+    CONF.system.disk_usage.crit_percent,
+  );
+  if warn.is_some() || crit.is_some() {
+    let value = disk_percent();
+    metrics.push(Metric {
+      name: "disk",
+      value,
+      unit: "%",
+      warn,
+      crit,
+      max: Some(100.0),
+      message: format!("disk at {:.0}%", value.unwrap_or_default()),
+    });
+  }
+
+  let (warn, crit) = (
+    CONF.packages.update_count.warn.map(f64::from),
+    CONF.packages.update_count.crit.map(f64::from),
+  );
+  if warn.is_some() || crit.is_some() {
+    let value = count_updates().await.map(f64::from);
+    metrics.push(Metric {
+      name: "updates",
+      value,
+      unit: "",
+      warn,
+      crit,
+      max: None,
+      message: format!("{:.0} updates pending", value.unwrap_or_default()),
+    });
+  }
+
+  let (warn, crit) = (
+    CONF.check.failed_services_warn.map(f64::from),
+    CONF.check.failed_services_crit.map(f64::from),
+  );
+  if warn.is_some() || crit.is_some() {
+    let value = count_failed_services().await;
+    metrics.push(Metric {
+      name: "failed_services",
+      value,
+      unit: "",
+      warn,
+      crit,
+      max: None,
+      message: format!("{:.0} failed services", value.unwrap_or_default()),
+    });
+  }
+
+  if metrics.is_empty() {
+    println!("DRACONIS UNKNOWN - no thresholds configured");
+    return 3;
+  }
+
+  let mut worst = Level::Ok;
+  let mut problems = Vec::new();
+  let mut unknown = Vec::new();
+  for metric in &metrics {
+    match metric.value {
+      Some(value) => {
+        let level = level(value, metric.warn, metric.crit);
+        if level != Level::Ok {
+          problems.push(metric.message.as_str());
+        }
+        worst = worst.max(level);
+      }
+      None => unknown.push(metric.name),
+    }
+  }
+
+  let (status, code) = match worst {
+    Level::Critical => ("CRITICAL", 2),
+    Level::Warning => ("WARNING", 1),
+    Level::Ok if !unknown.is_empty() => ("UNKNOWN", 3),
+    Level::Ok => ("OK", 0),
+  };
+
+  let summary = match code {
+    0 => metrics
+      .iter()
+      .map(|metric| metric.message.as_str())
+      .collect::<Vec<_>>()
+      .join(", "),
+    3 => format!("could not read {}", unknown.join(", ")),
+    _ => problems.join(", "),
+  };
+  let perfdata = metrics
+    .iter()
+    .filter_map(Metric::perfdata)
+    .collect::<Vec<_>>()
+    .join(" ");
+
+  println!("DRACONIS {status} - {summary} | {perfdata}");
+  code
+}
@@ -0,0 +1,291 @@
+use {
+  crate::util::{
+    conf_structs::Config,
+    config::{config_paths, shadowed_configs, user_config_path},
+    statics::{ARGS, CONF},
+  },
+  argparse::{ArgumentParser, StoreTrue},
+  std::io::{stderr, stdin, stdout, Write},
+};
+
+static TEMPLATE: &str = include_str!("../../example_config.toml");
+
+// The answers `init --interactive` accepts, `none` turns the icons off
+static ICONS: [&str; 3] = ["normal", "emoji", "none"];
+static TIME_FORMATS: [&str; 2] = ["12h", "24h"];
+static UNITS: [&str; 3] = ["metric", "imperial", "standard"];
+
+/// Sets `key` in `[section]` of the annotated template, keeping its comment. `None`
+/// comments the line out so the default applies.
+fn set_value(template: &str, section: &str, key: &str, value: Option<&str>) -> String {
+  let mut current = "";
+  let mut lines: Vec<String> = template
+    .lines()
+    .map(|line| {
+      let trimmed = line.trim();
+      if trimmed.starts_with('[') {
+        current = trimmed
+          .trim_start_matches('[')
+          .split(']')
+          .next()
+          .unwrap_or_default();
+        return line.to_string();
+      }
+      if current != section || line.split('=').next().map(str::trim) != Some(key) {
+        return line.to_string();
+      }
+
+      match value {
+        Some(value) => {
+          let comment = line.find(" #").map_or("", |i| &line[i..]);
+          format!("{key} = {value}{comment}")
+        }
+        None => format!("# {line}"),
+      }
+    })
+    .collect();
+  lines.push(String::new());
+
+  lines.join("\n")
+}
+
+fn ask(question: &str, default: &str) -> String {
+  if default.is_empty() {
+    print!("{question}: ");
+  } else {
+    print!("{question} [{default}]: ");
+  }
+  let _ = stdout().flush();
+
+  let mut answer = String::new();
+  let _ = stdin().read_line(&mut answer);
+  match answer.trim() {
+    "" => default.to_string(),
+    answer => answer.to_string(),
+  }
+}
+
+/// Asks until the answer is one of `choices`, ignoring case, so a typo can't write a
+/// config that fails to load.
+fn ask_choice(question: &str, choices: &[&str], default: &str) -> String {
+  let (last, rest) = choices.split_last().unwrap_or((&"", &[]));
+  let listed = format!("{} or {last}", rest.join(", "));
+  loop {
+    let answer = ask(&format!("{question} ({listed})"), default).to_lowercase();
+    if choices.contains(&answer.as_str()) {
+      return answer;
+    }
+    eprintln!("Please answer {listed}.");
+  }
+}
+
+fn quote(text: &str) -> String {
+  toml::Value::String(text.to_string()).to_string()
+}
+
+fn init(args: Vec<String>) -> i32 {
+  let mut force = false;
+  let mut interactive = false;
+  {
+    let mut ap = ArgumentParser::new();
+    ap.set_description("Write an annotated config file");
+    ap.refer(&mut force).add_option(
+      &["-f", "--force"],
+      StoreTrue,
+      "Overwrite an existing config",
+    );
+    ap.refer(&mut interactive).add_option(
+      &["-i", "--interactive"],
+      StoreTrue,
+      "Ask for the most common settings",
+    );
+    if let Err(code) = ap.parse(args, &mut stdout(), &mut stderr()) {
+      return code;
+    }
+  }
+
+  let Some(path) = user_config_path() else {
+    eprintln!("Could not find your config directory, set $XDG_CONFIG_HOME or pass --config.");
+    return 1;
+  };
+  // The template is TOML, so a config in another format is never overwritten
+  let toml = path
+    .extension()
+    .is_some_and(|extension| extension == "toml");
+  if path.exists() && !toml {
+    eprintln!(
+      "{} already exists, remove it first to write a TOML config.",
+      path.display()
+    );
+    return 1;
+  }
+  if path.exists() && !force {
+    eprintln!(
+      "{} already exists, pass --force to overwrite it.",
+      path.display()
+    );
+    return 1;
+  }
+
+  // The example describes a specific machine, so its personal values go back to defaults
+  let mut config = TEMPLATE.to_string();
+  for (section, key) in [
+    ("main", "hostname"),
+    ("main", "name"),
+    ("weather.values", "api_key"),
+    ("weather.values", "location"),
+  ] {
+    config = set_value(&config, section, key, None);
+  }
+  config = set_value(&config, "weather", "enabled", Some("false"));
+
+  if interactive {
+    let name = ask("Name to greet, leave empty for your real name", "");
+    if !name.is_empty() {
+      config = set_value(&config, "main", "name", Some(&quote(&name)));
+    }
+
+    match ask_choice("Icons", &ICONS, "normal").as_str() {
+      "none" => config = set_value(&config, "icons", "enabled", Some("false")),
+      kind => config = set_value(&config, "icons", "kind", Some(&quote(kind))),
+    }
+    let time = ask_choice("Time format", &TIME_FORMATS, "12h");
+    config = set_value(&config, "time", "kind", Some(&quote(&time)));
+
+    let api_key = ask("OpenWeatherMap API key, leave empty to disable weather", "");
+    if !api_key.is_empty() {
+      let location = ask("Location", "New York");
+      let units = ask_choice("Units", &UNITS, "metric");
+      config = set_value(&config, "weather", "enabled", Some("true"));
+      for (key, value) in [
+        ("api_key", api_key),
+        ("location", location),
+        ("units", units),
+      ] {
+        config = set_value(&config, "weather.values", key, Some(&quote(&value)));
+      }
+    }
+  }
+
+  if let Some(dir) = path.parent()
+    && let Err(e) = std::fs::create_dir_all(dir)
+  {
+    eprintln!("Could not create {}: {e}", dir.display());
+    return 1;
+  }
+  if let Err(e) = std::fs::write(&path, config) {
+    eprintln!("Could not write {}: {e}", path.display());
+    return 1;
+  }
+
+  println!("Wrote {}", path.display());
+  0
+}
+
+fn loaded_paths() -> Vec<String> {
+  config_paths()
+    .iter()
+    .filter(|path| path.exists())
+    .map(|path| path.display().to_string())
+    .collect()
+}
+
+/// Runs `draconis config <init|check|show|schema|path>`, returning the exit code.
+#[tracing::instrument]
+pub(crate) fn run_config() -> i32 {
+  let Some(subcommand) = ARGS.arguments.first() else {
+    eprintln!("Usage: draconis config <init|check|show|schema|path>");
+    return 2;
+  };
+  let mut args = ARGS.arguments.clone();
+  args[0] = format!("draconis config {subcommand}");
+
+  match subcommand.as_str() {
+    "init" => init(args),
+    "check" => {
+      // Loading the config reports the first error and exits
+      once_cell::sync::Lazy::force(&CONF);
+      match loaded_paths().as_slice() {
+        [] => println!("No config file found, using the defaults."),
+        paths => println!("Config is valid: {}", paths.join(", ")),
+      }
+      0
+    }
+    "show" => {
+      match toml::Value::try_from(&*CONF).and_then(|value| toml::to_string_pretty(&value)) {
+        Ok(config) => {
+          print!("{config}");
+          0
+        }
+        Err(e) => {
+          eprintln!("Could not print the config: {e}");
+          1
+        }
+      }
+    }
+    "schema" => {
+      let schema = schemars::schema_for!(Config);
+      match serde_json::to_string_pretty(&schema) {
+        Ok(schema) => {
+          println!("{schema}");
+          0
+        }
+        Err(e) => {
+          eprintln!("Could not print the schema: {e}");
+          1
+        }
+      }
+    }
+    "path" => {
+      for path in config_paths() {
+        if path.exists() {
+          println!("{}", path.display());
+        } else {
+          println!("{} (not found)", path.display());
+        }
+        for other in shadowed_configs(&path) {
+          println!("{} (ignored)", other.display());
+        }
+      }
+      0
+    }
+    other => {
+      eprintln!("Unknown config command: {other}");
+      2
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use {
+    super::*,
+    crate::util::conf_structs::{IconKind, TimeFormat, Units},
+    serde::de::DeserializeOwned,
+  };
+
+  fn parses<T: DeserializeOwned>(value: &str) -> bool {
+    toml::Value::String(value.to_string())
+      .try_into::<T>()
+      .is_ok()
+  }
+
+  #[test]
+  fn interactive_choices_are_valid_values() {
+    assert!(ICONS
+      .iter()
+      .filter(|kind| **kind != "none")
+      .all(|kind| parses::<IconKind>(kind)));
+    assert!(TIME_FORMATS.iter().all(|time| parses::<TimeFormat>(time)));
+    assert!(UNITS.iter().all(|units| parses::<Units>(units)));
+  }
+
+  #[test]
+  fn set_value_keeps_the_comment() {
+    let template = "[time]\nkind = \"12h\" # 12h or 24h\n";
+    assert_eq!(
+      set_value(template, "time", "kind", Some("\"24h\"")),
+      "[time]\nkind = \"24h\" # 12h or 24h\n"
+    );
+  }
+}
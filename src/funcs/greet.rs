@@ -1,5 +1,9 @@
 use {
-  crate::util::statics::{CONF, GREET_EMOJIS, GREET_ICONS},
+  crate::util::{
+    conf_structs::IconKind,
+    statics::{CONF, GREET_EMOJIS, GREET_ICONS},
+    terminal::icons_enabled,
+  },
   chrono::{Local, Timelike},
   sys_info::hostname,
   whoami::{realname, username},
@@ -25,21 +29,21 @@ pub(crate) fn greeting() -> Option<String> {
     CONF.main.name.as_ref()?.to_string()
   };
 
-  let phrase = if CONF.icons.enabled {
-    match CONF.icons.kind.as_deref() {
-      Some("emoji") => match Local::now().hour() {
+  let phrase = if icons_enabled() {
+    match CONF.icons.kind {
+      Some(IconKind::Emoji) => match Local::now().hour() {
         6..=11 => format!("{} Good morning", GREET_EMOJIS[0]),
         12..=17 => format!("{} Good afternoon", GREET_EMOJIS[1]),
         18..=22 => format!("{} Good evening", GREET_EMOJIS[2]),
         _ => format!("{} Good night", GREET_EMOJIS[3]),
       },
-      Some("normal") => match Local::now().hour() {
+      Some(IconKind::Normal) => match Local::now().hour() {
         6..=11 => format!("{} Good morning", GREET_ICONS[0]),
         12..=17 => format!("{} Good afternoon", GREET_ICONS[1]),
         18..=22 => format!("{} Good evening", GREET_ICONS[2]),
         _ => format!("{} Good night", GREET_ICONS[3]),
       },
-      Some(&_) | None => match Local::now().hour() {
+      None => match Local::now().hour() {
         6..=11 => "Good morning".to_string(),
         12..=17 => "Good afternoon".to_string(),
         18..=22 => "Good evening".to_string(),
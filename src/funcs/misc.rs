@@ -1,26 +1,42 @@
 use std::process::Command;
 
 use {
-  crate::util::statics::{CONF, TIME_EMOJIS, TIME_ICONS, WEATHER_EMOJIS, WEATHER_ICONS},
-  chrono::{Local, Timelike},
+  crate::util::{
+    conf_structs::{IconKind, PaletteLayout, SongMode, TimeFormat, Units},
+    formatting::{upper_first, visible_width},
+    statics::{CONF, TIME_EMOJIS, TIME_ICONS, WEATHER_EMOJIS, WEATHER_ICONS},
+    terminal::icons_enabled,
+  },
+  chrono::{Local, TimeZone, Timelike},
   mpris::PlayerFinder,
-  openweathermap::weather,
+  openweathermap::{weather, CurrentWeather},
   substring::Substring,
 };
 
 #[tracing::instrument]
-pub(crate) fn get_song() -> Option<String> {
+pub(crate) fn get_song() -> Option<(String, Option<String>)> {
   if !CONF.song.enabled {
     return None;
   }
 
   let songname;
-  if CONF.song.mode == Some("playerctl".into()) {
+  let url;
+  if CONF.song.mode == Some(SongMode::Playerctl) {
     let song = Command::new("playerctl")
-      .args(["metadata", "-f", "{{ artist }} - {{ title }}"])
+      .args([
+        "metadata",
+        "-f",
+        "{{ artist }} - {{ title }}\t{{ xesam:url }}",
+      ])
       .output()
       .unwrap();
-    songname = String::from_utf8_lossy(song.stdout.as_slice()).to_string();
+    let output = String::from_utf8_lossy(song.stdout.as_slice()).to_string();
+    let (name, link) = output
+      .trim_end_matches('\n')
+      .split_once('\t')
+      .unwrap_or((output.as_str(), ""));
+    songname = name.to_string();
+    url = Some(link.to_string()).filter(|link| !link.is_empty());
     if songname.is_empty() {
       return None;
     }
@@ -29,11 +45,16 @@ pub(crate) fn get_song() -> Option<String> {
     let song = player.first()?.get_metadata().ok()?; // this is blocking
     let artists = song.artists()?.join(", ");
     songname = format!("{} - {}", artists, song.title()?);
+    url = song.url().map(str::to_string);
   }
 
-  if CONF.icons.enabled {
-    match CONF.icons.kind.as_deref() {
-      Some("emoji") => {
+  Some((truncate_song(songname)?, url))
+}
+
+fn truncate_song(songname: String) -> Option<String> {
+  if icons_enabled() {
+    match CONF.icons.kind {
+      Some(IconKind::Emoji) => {
         if songname.len() > (CONF.util.width as usize) {
           Some(format!(
             "{}...",
@@ -43,7 +64,7 @@ pub(crate) fn get_song() -> Option<String> {
           Some(songname.trim_end_matches('\n').to_string())
         }
       }
-      Some(&_) | None => {
+      Some(IconKind::Normal) | None => {
         if songname.len() > (CONF.util.width as usize) {
           Some(format!(
             "{}...",
@@ -64,8 +85,9 @@ pub(crate) fn get_song() -> Option<String> {
   }
 }
 
+/// Fetches the current weather once, the row and the detailed view are both built from it.
 #[tracing::instrument]
-pub(crate) async fn get_weather() -> Option<String> {
+pub(crate) async fn fetch_weather() -> Option<CurrentWeather> {
   if CONF.weather.values.api_key.is_none()
     || CONF.weather.values.lang.is_none()
     || CONF.weather.values.location.is_none()
@@ -78,123 +100,214 @@ pub(crate) async fn get_weather() -> Option<String> {
   let api_key = CONF.weather.values.api_key.as_ref().unwrap().as_str();
   let lang = CONF.weather.values.lang.as_ref().unwrap().as_str();
   let location = CONF.weather.values.location.as_ref().unwrap().as_str();
-  let units = CONF.weather.values.units.as_ref().unwrap().as_str();
-
-  match &weather(location, units, lang, api_key).await {
-    Ok(current) => {
-      let deg = if units.trim_matches('\"') == "imperial" {
-        "F"
-      } else {
-        "C"
-      };
-      let icon_code = &current.weather[0].icon;
-      let icon = if CONF.icons.enabled {
-        match CONF.icons.kind.as_deref() {
-          Some("emoji") => {
-            match icon_code.as_ref() {
-              "01d" => WEATHER_EMOJIS[0], // Clear sky
-              "01n" => WEATHER_EMOJIS[1],
-              "02d" => WEATHER_EMOJIS[2], // Few clouds
-              "02n" => WEATHER_EMOJIS[3],
-              "03d" => WEATHER_EMOJIS[4], // Scattered clouds
-              "03n" => WEATHER_EMOJIS[5],
-              "04d" => WEATHER_EMOJIS[6], // Broken clouds
-              "04n" => WEATHER_EMOJIS[7],
-              "09d" => WEATHER_EMOJIS[8], // Shower rain
-              "09n" => WEATHER_EMOJIS[9],
-              "10d" => WEATHER_EMOJIS[10], // Rain
-              "10n" => WEATHER_EMOJIS[11],
-              "11d" => WEATHER_EMOJIS[12], // Thunderstorm
-              "11n" => WEATHER_EMOJIS[13],
-              "13d" => WEATHER_EMOJIS[14], // Snow
-              "13n" => WEATHER_EMOJIS[15],
-              "40d" => WEATHER_EMOJIS[16], // Mist
-              "40n" => WEATHER_EMOJIS[17],
-              "50d" => WEATHER_EMOJIS[18], // Fog
-              "50n" => WEATHER_EMOJIS[19],
-              _ => WEATHER_EMOJIS[20], // Unknown
-            }
-          }
-          Some("normal") => match icon_code.as_ref() {
-            "01d" => WEATHER_ICONS[0],
-            "01n" => WEATHER_ICONS[1],
-            "02d" => WEATHER_ICONS[2],
-            "02n" => WEATHER_ICONS[3],
-            "03d" => WEATHER_ICONS[4],
-            "03n" => WEATHER_ICONS[5],
-            "04d" => WEATHER_ICONS[6],
-            "04n" => WEATHER_ICONS[7],
-            "09d" => WEATHER_ICONS[8],
-            "09n" => WEATHER_ICONS[9],
-            "10d" => WEATHER_ICONS[10],
-            "10n" => WEATHER_ICONS[11],
-            "11d" => WEATHER_ICONS[12],
-            "11n" => WEATHER_ICONS[13],
-            "13d" => WEATHER_ICONS[14],
-            "13n" => WEATHER_ICONS[15],
-            "40d" => WEATHER_ICONS[16],
-            "40n" => WEATHER_ICONS[17],
-            "50d" => WEATHER_ICONS[18],
-            "50n" => WEATHER_ICONS[19],
-            _ => WEATHER_ICONS[20],
-          },
-          Some(&_) | None => "",
-        }
-      } else {
-        ""
-      };
-
-      let main = current.weather[0].main.to_string();
-      let temp = current.main.temp.to_string();
-
-      if CONF.icons.enabled {
-        match CONF.icons.kind.as_deref() {
-          Some("emoji") | Some("normal") => Some(format!(
-            "│ {} {} {}°{}",
-            icon,
-            main,
-            temp.substring(0, 2),
-            deg
-          )),
-          Some(&_) | None => Some(format!(
-            "│{} {} {}°{}",
-            icon,
-            main,
-            temp.substring(0, 2),
-            deg
-          )),
-        }
-      } else {
-        Some(format!(
-          "│{} {} {}°{}",
-          icon,
-          main,
-          temp.substring(0, 2),
-          deg
-        ))
-      }
-    }
-    Err(e) => {
+  let units = CONF.weather.values.units.unwrap().as_str();
+
+  weather(location, units, lang, api_key)
+    .await
+    .map_err(|e| {
       tracing::warn!(
         "Could not fetch weather because: {} - maybe you forgot an API key?",
         e
-      );
-      None
+      )
+    })
+    .ok()
+}
+
+#[tracing::instrument]
+pub(crate) fn get_weather(current: &CurrentWeather) -> Option<(&'static str, String)> {
+  let units = CONF.weather.values.units?;
+
+  let deg = if units == Units::Imperial { "F" } else { "C" };
+  let icon_code = &current.weather[0].icon;
+  let icon = if icons_enabled() {
+    match CONF.icons.kind {
+      Some(IconKind::Emoji) => {
+        match icon_code.as_ref() {
+          "01d" => WEATHER_EMOJIS[0], // Clear sky
+          "01n" => WEATHER_EMOJIS[1],
+          "02d" => WEATHER_EMOJIS[2], // Few clouds
+          "02n" => WEATHER_EMOJIS[3],
+          "03d" => WEATHER_EMOJIS[4], // Scattered clouds
+          "03n" => WEATHER_EMOJIS[5],
+          "04d" => WEATHER_EMOJIS[6], // Broken clouds
+          "04n" => WEATHER_EMOJIS[7],
+          "09d" => WEATHER_EMOJIS[8], // Shower rain
+          "09n" => WEATHER_EMOJIS[9],
+          "10d" => WEATHER_EMOJIS[10], // Rain
+          "10n" => WEATHER_EMOJIS[11],
+          "11d" => WEATHER_EMOJIS[12], // Thunderstorm
+          "11n" => WEATHER_EMOJIS[13],
+          "13d" => WEATHER_EMOJIS[14], // Snow
+          "13n" => WEATHER_EMOJIS[15],
+          "40d" => WEATHER_EMOJIS[16], // Mist
+          "40n" => WEATHER_EMOJIS[17],
+          "50d" => WEATHER_EMOJIS[18], // Fog
+          "50n" => WEATHER_EMOJIS[19],
+          _ => WEATHER_EMOJIS[20], // Unknown
+        }
+      }
+      Some(IconKind::Normal) => match icon_code.as_ref() {
+        "01d" => WEATHER_ICONS[0],
+        "01n" => WEATHER_ICONS[1],
+        "02d" => WEATHER_ICONS[2],
+        "02n" => WEATHER_ICONS[3],
+        "03d" => WEATHER_ICONS[4],
+        "03n" => WEATHER_ICONS[5],
+        "04d" => WEATHER_ICONS[6],
+        "04n" => WEATHER_ICONS[7],
+        "09d" => WEATHER_ICONS[8],
+        "09n" => WEATHER_ICONS[9],
+        "10d" => WEATHER_ICONS[10],
+        "10n" => WEATHER_ICONS[11],
+        "11d" => WEATHER_ICONS[12],
+        "11n" => WEATHER_ICONS[13],
+        "13d" => WEATHER_ICONS[14],
+        "13n" => WEATHER_ICONS[15],
+        "40d" => WEATHER_ICONS[16],
+        "40n" => WEATHER_ICONS[17],
+        "50d" => WEATHER_ICONS[18],
+        "50n" => WEATHER_ICONS[19],
+        _ => WEATHER_ICONS[20],
+      },
+      None => "",
+    }
+  } else {
+    ""
+  };
+
+  let main = current.weather[0].main.to_string();
+  let temp = current.main.temp.to_string();
+
+  Some((icon, format!("{} {}°{}", main, temp.substring(0, 2), deg)))
+}
+
+fn encode_url(text: &str) -> String {
+  text
+    .bytes()
+    .map(|byte| match byte {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+        (byte as char).to_string()
+      }
+      byte => format!("%{byte:02X}"),
+    })
+    .collect()
+}
+
+/// Link to the forecast of the configured location, used when hyperlinks are supported.
+pub(crate) fn get_weather_url() -> Option<String> {
+  if !CONF.weather.enabled {
+    return None;
+  }
+
+  let location = CONF.weather.values.location.as_ref()?;
+  Some(format!(
+    "https://openweathermap.org/find?q={}",
+    encode_url(location)
+  ))
+}
+
+#[tracing::instrument]
+pub(crate) fn get_weather_details(current: &CurrentWeather) -> Option<Vec<String>> {
+  let units = CONF.weather.values.units?;
+
+  let (deg, speed) = if units == Units::Imperial {
+    ("F", "mph")
+  } else {
+    ("C", "m/s")
+  };
+  let sun = |timestamp: i64| {
+    Local
+      .timestamp_opt(timestamp, 0)
+      .single()
+      .map(|dt| dt.format("%H:%M").to_string())
+      .unwrap_or_else(|| "??:??".to_string())
+  };
+
+  Some(vec![
+    format!(
+      "│ {}, {}°{} (feels like {}°{})",
+      upper_first(current.weather[0].description.to_string()),
+      current.main.temp.round(),
+      deg,
+      current.main.feels_like.round(),
+      deg
+    ),
+    format!(
+      "│ High {}°{} / Low {}°{}",
+      current.main.temp_max.round(),
+      deg,
+      current.main.temp_min.round(),
+      deg
+    ),
+    format!("│ Humidity {}%", current.main.humidity),
+    format!("│ Wind {} {}", current.wind.speed, speed),
+    format!("│ Pressure {} hPa", current.main.pressure),
+    format!(
+      "│ Sunrise {}, sunset {}",
+      sun(current.sys.sunrise),
+      sun(current.sys.sunset)
+    ),
+  ])
+}
+
+#[tracing::instrument]
+pub(crate) fn toggle_playback() {
+  if CONF.song.mode == Some(SongMode::Playerctl) {
+    if let Err(e) = Command::new("playerctl").arg("play-pause").status() {
+      tracing::warn!("Could not run playerctl: {}", e);
+    }
+    return;
+  }
+
+  let players = PlayerFinder::new()
+    .ok()
+    .and_then(|finder| finder.find_all().ok())
+    .unwrap_or_default();
+  if let Some(player) = players.first()
+    && let Err(e) = player.play_pause()
+  {
+    tracing::warn!("Could not toggle playback: {}", e);
+  }
+}
+
+/// Returns the 16 terminal colors as rows of blocks, the normal colors first.
+#[tracing::instrument]
+pub(crate) fn get_palette() -> Vec<String> {
+  if !CONF.palette.enabled {
+    return Vec::new();
+  }
+
+  let block = CONF.palette.block.as_deref().unwrap_or("███");
+  let swatches = |base: u8| {
+    (base..base + 8)
+      .map(|code| format!("\x1b[{code}m{block}"))
+      .collect::<String>()
+      + "\x1b[0m"
+  };
+
+  // A single row needs 16 blocks next to the "│ " margin, wider ones are split
+  let fits = 16 * visible_width(block) + 2 <= (CONF.util.width as usize).saturating_sub(5);
+
+  match CONF.palette.layout {
+    Some(PaletteLayout::Single) if fits => vec![swatches(30) + &swatches(90)],
+    Some(PaletteLayout::Single) => {
+      tracing::info!("The palette is too wide for one row, splitting it");
+      vec![swatches(30), swatches(90)]
     }
+    Some(PaletteLayout::Split) | None => vec![swatches(30), swatches(90)],
   }
 }
 
 #[tracing::instrument]
-pub(crate) fn get_datetime() -> Option<String> {
+pub(crate) fn get_datetime() -> Option<(&'static str, String)> {
   if !CONF.time.enabled {
     return None;
   }
 
   let dt = Local::now();
-  let time = match CONF.time.kind.as_deref()? {
-    "12h" => dt.format("%l:%M %p").to_string(),
-    "24h" => dt.format("%H:%M").to_string(),
-    _ => "off".to_string(),
+  let time = match CONF.time.kind? {
+    TimeFormat::TwelveHour => dt.format("%l:%M %p").to_string(),
+    TimeFormat::TwentyFourHour => dt.format("%H:%M").to_string(),
   };
   let day = dt.format("%e").to_string();
   let date = match day.trim_start_matches(' ') {
@@ -203,9 +316,9 @@ pub(crate) fn get_datetime() -> Option<String> {
     "3" | "23" => format!("{} {}rd", dt.format("%B"), day.trim_start_matches(' ')),
     _ => format!("{} {}th", dt.format("%B"), day.trim_start_matches(' ')),
   };
-  let time_icon = if CONF.icons.enabled {
-    match CONF.icons.kind.as_deref() {
-      Some("emoji") => {
+  let time_icon = if icons_enabled() {
+    match CONF.icons.kind {
+      Some(IconKind::Emoji) => {
         let index: usize = if dt.hour() >= 12 {
           (dt.hour() - 12).try_into().unwrap()
         } else {
@@ -213,7 +326,7 @@ pub(crate) fn get_datetime() -> Option<String> {
         };
         TIME_EMOJIS[index.min(11)]
       }
-      Some("normal") => {
+      Some(IconKind::Normal) => {
         let index: usize = if dt.hour() >= 12 {
           (dt.hour() - 12).try_into().unwrap()
         } else {
@@ -221,33 +334,14 @@ pub(crate) fn get_datetime() -> Option<String> {
         };
         TIME_ICONS[index.min(11)]
       }
-      Some(&_) | None => "",
+      None => "",
     }
   } else {
     ""
   };
 
-  if CONF.icons.enabled {
-    match CONF.icons.kind.as_deref() {
-      Some("emoji") | Some("normal") => Some(format!(
-        "│ {} {}, {}",
-        time_icon,
-        date,
-        time.trim_start_matches(' ')
-      )),
-      Some(&_) | None => Some(format!(
-        "│{} {}, {}",
-        time_icon,
-        date,
-        time.trim_start_matches(' ')
-      )),
-    }
-  } else {
-    Some(format!(
-      "│{} {}, {}",
-      time_icon,
-      date,
-      time.trim_start_matches(' ')
-    ))
-  }
+  Some((
+    time_icon,
+    format!("{}, {}", date, time.trim_start_matches(' ')),
+  ))
 }
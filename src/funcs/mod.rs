@@ -1,4 +1,9 @@
+pub mod check;
+pub mod config;
 pub mod greet;
 pub mod misc;
 pub mod pkgs;
+pub mod prompt;
+pub mod render;
 pub mod system_info;
+pub mod thresholds;
@@ -1,7 +1,11 @@
 use std::env;
 
 use {
-  crate::util::statics::{CONF, PACKAGE_EMOJIS, PACKAGE_ICONS},
+  crate::util::{
+    conf_structs::{IconKind, PackageManager},
+    statics::{CONF, PACKAGE_EMOJIS, PACKAGE_ICONS},
+    terminal::icons_enabled,
+  },
   std::process::Stdio,
   tokio::{
     io::{AsyncBufReadExt, BufReader},
@@ -9,18 +13,6 @@ use {
   },
 };
 
-#[derive(Debug)]
-enum CommandKind {
-  Pacman,
-  Apt,
-  Xbps,
-  Portage,
-  Apk,
-  Dnf,
-  NixUser,
-  NixSystem,
-}
-
 async fn count_lines(skip: i32, mut reader: BufReader<ChildStdout>) -> Option<i32> {
   let mut total = 0;
   let mut s = String::new();
@@ -46,51 +38,51 @@ async fn count_lines(skip: i32, mut reader: BufReader<ChildStdout>) -> Option<i3
   }
 }
 
-fn check_update_commmand(command: String) -> Option<(CommandKind, Command)> {
+fn check_update_commmand(manager: PackageManager) -> Option<Command> {
   if !CONF.packages.update_count.enabled {
     return None;
   }
 
-  let tup = match command.as_str() {
-    "pacman" => (CommandKind::Pacman, Command::new("checkupdates")),
-    "apt" => (CommandKind::Apt, {
+  let command = match manager {
+    PackageManager::Pacman => Command::new("checkupdates"),
+    PackageManager::Apt => {
       let mut command = Command::new("apt");
       command.args(["list", "-u"]);
 
       command
-    }),
-    "xbps" => (CommandKind::Xbps, {
+    }
+    PackageManager::Xbps => {
       let mut command = Command::new("xbps-install");
       command.arg("-Sun");
       command
-    }),
-    "portage" => (CommandKind::Portage, {
+    }
+    PackageManager::Portage => {
       let mut command = Command::new("eix");
       command.args(["-u", "--format", "'<installedversions:nameversion>'"]);
       command
-    }),
-    "apk" => (CommandKind::Apk, {
+    }
+    PackageManager::Apk => {
       let mut command = Command::new("apk");
       command.args(["-u", "list"]);
       command
-    }),
-    "dnf" => (CommandKind::Dnf, {
+    }
+    PackageManager::Dnf => {
       let mut command = Command::new("dnf");
       command.arg("check-update");
       command
-    }),
-    other => {
-      tracing::warn!("Unsupported package manager: {}", other);
+    }
+    PackageManager::NixUser | PackageManager::NixSystem => {
+      tracing::warn!("Update counting is not supported for {:?}", manager);
       return None;
     }
   };
 
-  Some(tup)
+  Some(command)
 }
 
 #[tracing::instrument]
-async fn do_update_counting(arg: String) -> Option<i32> {
-  let (kind, mut command) = check_update_commmand(arg)?;
+async fn do_update_counting(manager: PackageManager) -> Option<i32> {
+  let mut command = check_update_commmand(manager)?;
   let reader = command
     .stderr(Stdio::null())
     .stdout(Stdio::piped())
@@ -100,138 +92,113 @@ async fn do_update_counting(arg: String) -> Option<i32> {
     .take()?;
 
   let fs = BufReader::new(reader);
-  match kind {
-    CommandKind::Apt => count_lines(2, fs).await,
-    CommandKind::Portage => Some(0), // FIXME: Portage needs a proper update count command
-    CommandKind::Dnf => count_lines(3, fs).await,
+  match manager {
+    PackageManager::Apt => count_lines(2, fs).await,
+    PackageManager::Portage => Some(0), // FIXME: Portage needs a proper update count command
+    PackageManager::Dnf => count_lines(3, fs).await,
     _ => count_lines(0, fs).await,
   }
 }
 
 async fn check_updates() -> Option<i32> {
-  match &CONF.packages.package_managers {
-    Some(toml::Value::Array(pm)) => {
-      let mut handles = Vec::new();
-
-      for arg in pm {
-        if let toml::Value::String(string) = arg {
-          let handle = tokio::spawn(do_update_counting(string.clone()));
-          handles.push(handle);
-        }
-      }
+  if CONF.packages.package_managers.is_empty() {
+    return None;
+  }
 
-      let mut total_updates = 0;
+  let mut handles = Vec::new();
+  for manager in &CONF.packages.package_managers {
+    let handle = tokio::spawn(do_update_counting(*manager));
+    handles.push(handle);
+  }
 
-      for handle in handles {
-        total_updates += handle.await.ok()??;
-      }
+  let mut total_updates = 0;
 
-      Some(total_updates)
-    }
-    Some(toml::Value::String(pm)) => do_update_counting(pm.clone()).await,
-    _ => None,
+  for handle in handles {
+    total_updates += handle.await.ok()??;
   }
+
+  Some(total_updates)
 }
 
 #[tracing::instrument]
-pub(crate) async fn count_updates() -> Option<String> {
-  let count = check_updates().await?;
-  let updates = if CONF.icons.enabled {
-    match CONF.icons.kind.as_deref() {
-      Some("emoji") => match count {
-        0 => format!("{} Up to date", PACKAGE_EMOJIS[0]),
-        1 => format!("{} 1 update", PACKAGE_EMOJIS[1]),
-        2 => format!("{} 2 updates", PACKAGE_EMOJIS[2]),
-        3 => format!("{} 3 updates", PACKAGE_EMOJIS[3]),
-        4 => format!("{} 4 updates", PACKAGE_EMOJIS[4]),
-        5 => format!("{} 5 updates", PACKAGE_EMOJIS[5]),
-        6 => format!("{} 6 updates", PACKAGE_EMOJIS[6]),
-        7 => format!("{} 7 updates", PACKAGE_EMOJIS[7]),
-        8 => format!("{} 8 updates", PACKAGE_EMOJIS[8]),
-        9 => format!("{} 9 updates", PACKAGE_EMOJIS[9]),
-        10 => format!("{} 10 updates", PACKAGE_EMOJIS[10]),
-        _ => format!("{} {} updates", PACKAGE_EMOJIS[11], count),
-      },
-      Some("normal") => match count {
-        0 => format!("{} Up to date", PACKAGE_ICONS[0]),
-        1 => format!("{} 1 update", PACKAGE_ICONS[1]),
-        2 => format!("{} 2 updates", PACKAGE_ICONS[2]),
-        3 => format!("{} 3 updates", PACKAGE_ICONS[3]),
-        4 => format!("{} 4 updates", PACKAGE_ICONS[4]),
-        5 => format!("{} 5 updates", PACKAGE_ICONS[5]),
-        6 => format!("{} 6 updates", PACKAGE_ICONS[6]),
-        7 => format!("{} 7 updates", PACKAGE_ICONS[7]),
-        8 => format!("{} 8 updates", PACKAGE_ICONS[8]),
-        9 => format!("{} 9 updates", PACKAGE_ICONS[9]),
-        _ => format!("{} {} updates", PACKAGE_ICONS[10], count),
-      },
-      Some(&_) | None => format!("{count} updates"),
+pub(crate) async fn count_updates() -> Option<i32> {
+  check_updates().await
+}
+
+pub(crate) fn format_updates(count: i32) -> (&'static str, String) {
+  let icon = if icons_enabled() {
+    match CONF.icons.kind {
+      Some(IconKind::Emoji) => PACKAGE_EMOJIS[count.clamp(0, 11) as usize],
+      Some(IconKind::Normal) => PACKAGE_ICONS[count.clamp(0, 10) as usize],
+      None => "",
     }
   } else {
-    format!("{count} updates")
+    ""
   };
-  Some(format!("│ {updates}"))
+  let updates = match count {
+    0 => "Up to date".to_string(),
+    1 => "1 update".to_string(),
+    n => format!("{n} updates"),
+  };
+
+  (icon, updates)
 }
 
-fn check_installed_command(command: String) -> Option<(CommandKind, Command)> {
+fn check_installed_command(manager: PackageManager) -> Option<Command> {
   if !CONF.packages.package_count.enabled {
     return None;
   }
 
-  let tup = match command.as_str() {
-    "pacman" => (CommandKind::Pacman, {
+  let command = match manager {
+    PackageManager::Pacman => {
       let mut command = Command::new("pacman");
       command.arg("-Q");
       command
-    }),
-    "apt" => (CommandKind::Apt, {
+    }
+    PackageManager::Apt => {
       let mut command = Command::new("apt");
       command.args(["list", "-i"]);
       command
-    }),
-    "xbps" => (CommandKind::Xbps, {
+    }
+    PackageManager::Xbps => {
       let mut command = Command::new("xbps-query");
       command.arg("-l");
       command
-    }),
-    "portage" => (CommandKind::Portage, {
+    }
+    PackageManager::Portage => {
       let mut command = Command::new("qlist");
       command.arg("-I");
       command
-    }),
-    "apk" => (CommandKind::Apk, {
+    }
+    PackageManager::Apk => {
       let mut command = Command::new("apk");
       command.arg("info");
       command
-    }),
-    "dnf" => (CommandKind::Dnf, {
+    }
+    PackageManager::Dnf => {
       let mut command = Command::new("dnf");
       command.args(["list", "installed"]);
       command
-    }),
-    "nix-user" => (CommandKind::NixUser, {
+    }
+    PackageManager::NixUser => {
       let mut command = Command::new("nix-store");
       let user = env::var("USER").unwrap_or_else(|_| "UNKNOWN".to_string());
       command.args(["-qR", format!("/home/{user}/.nix-profile").as_str()]);
       command
-    }),
-    "nix-system" => (CommandKind::NixSystem, {
+    }
+    PackageManager::NixSystem => {
       let mut command = Command::new("nix-store");
       command.args(["-qR", "/run/current-system/sw"]);
       command
-    }),
-    other => {
-      tracing::warn!("unknown package manager: {}", other);
-      return None;
     }
   };
 
-  Some(tup)
+  Some(command)
 }
 
 #[tracing::instrument]
-async fn do_installed_counting(arg: String) -> Option<i32> {
-  let (kind, mut command) = check_installed_command(arg)?;
+async fn do_installed_counting(manager: PackageManager) -> Option<i32> {
+  let mut command = check_installed_command(manager)?;
   let reader = command
     .stderr(Stdio::null())
     .stdout(Stdio::piped())
@@ -241,34 +208,70 @@ async fn do_installed_counting(arg: String) -> Option<i32> {
     .take()?;
 
   let fs = BufReader::new(reader);
-  match kind {
-    CommandKind::Apt => count_lines(2, fs).await,
+  match manager {
+    PackageManager::Apt => count_lines(2, fs).await,
     _ => count_lines(0, fs).await,
   }
 }
 
 #[tracing::instrument]
 pub(crate) async fn get_package_count() -> Option<i32> {
-  match &CONF.packages.package_managers {
-    Some(toml::Value::Array(pm)) => {
-      let mut handles = Vec::new();
-
-      for arg in pm {
-        if let toml::Value::String(string) = arg {
-          let handle = tokio::spawn(do_installed_counting(string.to_owned()));
-          handles.push(handle);
-        }
-      }
+  if CONF.packages.package_managers.is_empty() {
+    return None;
+  }
 
-      let mut total_packages = 0;
+  let mut handles = Vec::new();
+  for manager in &CONF.packages.package_managers {
+    let handle = tokio::spawn(do_installed_counting(*manager));
+    handles.push(handle);
+  }
 
-      for handle in handles {
-        total_packages += handle.await.ok()??;
-      }
+  let mut total_packages = 0;
+
+  for handle in handles {
+    total_packages += handle.await.ok()??;
+  }
+
+  Some(total_packages)
+}
+
+fn upgrade_command(manager: PackageManager) -> &'static str {
+  match manager {
+    PackageManager::Pacman => "sudo pacman -Syu",
+    PackageManager::Apt => "sudo apt update && sudo apt upgrade",
+    PackageManager::Xbps => "sudo xbps-install -Su",
+    PackageManager::Portage => "sudo emerge --update --deep --newuse @world",
+    PackageManager::Apk => "sudo apk upgrade",
+    PackageManager::Dnf => "sudo dnf upgrade",
+    PackageManager::NixUser => "nix-env -u",
+    PackageManager::NixSystem => "sudo nixos-rebuild switch --upgrade",
+  }
+}
 
-      Some(total_packages)
+#[tracing::instrument]
+pub(crate) async fn run_upgrade() {
+  let commands = match &CONF.packages.upgrade_command {
+    Some(command) => vec![command.as_str()],
+    None => CONF
+      .packages
+      .package_managers
+      .iter()
+      .map(|manager| upgrade_command(*manager))
+      .collect(),
+  };
+
+  for command in commands {
+    println!("\x1b[1m:: {command}\x1b[0m");
+    match Command::new("sh").args(["-c", command]).status().await {
+      Ok(status) if !status.success() => {
+        tracing::warn!("Upgrade command `{}` exited with {}", command, status);
+        return;
+      }
+      Ok(_) => (),
+      Err(e) => {
+        tracing::warn!("Failed to run upgrade command `{}`: {}", command, e);
+        return;
+      }
     }
-    Some(toml::Value::String(pm)) => do_installed_counting(pm.clone()).await,
-    _ => None,
   }
 }
@@ -0,0 +1,92 @@
+use {
+  crate::{
+    funcs::{
+      misc::{get_weather_details, toggle_playback},
+      pkgs::run_upgrade,
+    },
+    util::{
+      formatting::{calc_bottom, calc_whitespace, calc_with_hostname},
+      statics::CONF,
+      terminal::adapt,
+    },
+  },
+  crossterm::{terminal, tty::IsTty},
+  openweathermap::CurrentWeather,
+  std::{
+    io::{stdin, stdout, Write},
+    time::Duration,
+  },
+};
+
+/// Waits up to `timeout` for a keypress and takes it only when a single byte is queued,
+/// so pastes, escape sequences and typing ahead stay queued for the shell. A terminal
+/// can't be peeked, so a single key that isn't bound is still read, it just ends the
+/// prompt without an action.
+fn read_key(timeout: Duration) -> Option<u8> {
+  if terminal::enable_raw_mode().is_err() {
+    return None;
+  }
+
+  let mut fd = libc::pollfd {
+    fd: libc::STDIN_FILENO,
+    events: libc::POLLIN,
+    revents: 0,
+  };
+  let timeout = timeout.as_millis().min(i32::MAX as u128) as i32;
+  let mut queued: libc::c_int = 0;
+  let mut key = 0u8;
+  // SAFETY: `fd`, `queued` and `key` outlive the calls and the read is one byte long.
+  let read = unsafe {
+    libc::poll(&mut fd, 1, timeout) > 0
+      && libc::ioctl(libc::STDIN_FILENO, libc::FIONREAD, &mut queued) == 0
+      && queued == 1
+      && libc::read(libc::STDIN_FILENO, (&mut key as *mut u8).cast(), 1) == 1
+  };
+  let _ = terminal::disable_raw_mode();
+
+  read.then_some(key)
+}
+
+#[tracing::instrument]
+pub(crate) async fn handle_keypress(weather: Option<&CurrentWeather>) {
+  if !CONF.prompt.enabled || !stdin().is_tty() || !stdout().is_tty() {
+    return;
+  }
+
+  let timeout = Duration::from_millis(CONF.prompt.timeout.unwrap_or(1500));
+
+  print!(
+    "{}",
+    adapt("\x1b[2m[u] upgrade  [m] play/pause  [w] weather\x1b[0m")
+  );
+  let _ = stdout().flush();
+  let key = tokio::task::spawn_blocking(move || read_key(timeout))
+    .await
+    .unwrap_or_default();
+  print!("\r\x1b[2K");
+  let _ = stdout().flush();
+
+  match key {
+    Some(b'u') => run_upgrade().await,
+    Some(b'm') => {
+      let _ = tokio::task::spawn_blocking(toggle_playback).await;
+    }
+    Some(b'w') => match weather.and_then(get_weather_details) {
+      Some(lines) => {
+        let location = CONF.weather.values.location.as_deref().unwrap_or_default();
+        println!(
+          "{}",
+          adapt(&calc_with_hostname(format!(
+            "╭─── \x1b[32m{location}\x1b[0m "
+          )))
+        );
+        for line in lines {
+          println!("{}", adapt(&calc_whitespace(line)));
+        }
+        println!("{}", adapt(&calc_bottom()));
+      }
+      None => eprintln!("Weather is not available, check the [weather] section of your config."),
+    },
+    _ => (),
+  }
+}
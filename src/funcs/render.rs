@@ -0,0 +1,56 @@
+use {
+  crate::util::{
+    render::{render_png, render_svg},
+    statics::ARGS,
+  },
+  argparse::{ArgumentParser, Store},
+  std::io::{stderr, stdout},
+};
+
+/// Writes the greeter box to the files given to `draconis render`, returning the exit code.
+#[tracing::instrument(skip(lines))]
+pub(crate) fn run_render(lines: &[String]) -> i32 {
+  let mut svg_path = String::new();
+  let mut png_path = String::new();
+  let mut font = "monospace".to_string();
+  {
+    let mut ap = ArgumentParser::new();
+    ap.set_description("Render the greeter to an image");
+    ap.refer(&mut svg_path)
+      .add_option(&["--svg"], Store, "Write an SVG image to this path");
+    ap.refer(&mut png_path)
+      .add_option(&["--png"], Store, "Write a PNG image to this path");
+    ap.refer(&mut font).add_option(
+      &["--font"],
+      Store,
+      "Font family to draw with, use a Nerd Font for icons",
+    );
+
+    let mut args = ARGS.arguments.clone();
+    args.insert(0, "draconis render".to_string());
+    if let Err(code) = ap.parse(args, &mut stdout(), &mut stderr()) {
+      return code;
+    }
+  }
+
+  if svg_path.is_empty() && png_path.is_empty() {
+    eprintln!("Nothing to render, pass --svg or --png with an output path.");
+    return 2;
+  }
+
+  let svg = render_svg(lines, &font);
+  if !svg_path.is_empty()
+    && let Err(e) = std::fs::write(&svg_path, &svg)
+  {
+    eprintln!("Could not write {svg_path}: {e}");
+    return 1;
+  }
+  if !png_path.is_empty()
+    && let Err(e) = render_png(&svg, &png_path)
+  {
+    eprintln!("Could not write {png_path}: {e}");
+    return 1;
+  }
+
+  0
+}
@@ -1,9 +1,11 @@
 use {
-  crate::util::statics::CONF,
+  crate::util::{
+    conf_structs::IconKind, formatting::calc_bar, statics::CONF, terminal::icons_enabled,
+  },
   std::env,
   substring::Substring,
   sys_info::{linux_os_release, os_release},
-  systemstat::{saturating_sub_bytes, Platform, System},
+  systemstat::{saturating_sub_bytes, ByteSize, Platform, System},
 };
 
 #[tracing::instrument]
@@ -14,9 +16,9 @@ pub(crate) fn get_release_blocking() -> Option<String> {
 
   let rel = linux_os_release().ok()?.pretty_name?; // this performs a blocking read of /etc/os-release
 
-  if CONF.icons.enabled {
-    match CONF.icons.kind.as_deref() {
-      Some("emoji") => {
+  if icons_enabled() {
+    match CONF.icons.kind {
+      Some(IconKind::Emoji) => {
         if rel.len() > 41 {
           Some(format!("{}...", rel.trim_matches('\"').substring(0, 37)))
         } else {
@@ -29,7 +31,7 @@ pub(crate) fn get_release_blocking() -> Option<String> {
           )
         }
       }
-      Some(&_) | None => {
+      Some(IconKind::Normal) | None => {
         if rel.len() > 42 {
           Some(format!("{}...", rel.trim_matches('\"').substring(0, 38)))
         } else {
@@ -56,6 +58,16 @@ pub(crate) fn get_release_blocking() -> Option<String> {
   }
 }
 
+#[tracing::instrument]
+pub(crate) fn get_release_url_blocking() -> Option<String> {
+  if !CONF.system.release.enabled {
+    return None;
+  }
+
+  let url = linux_os_release().ok()?.home_url?;
+  Some(url.trim_matches('\"').to_string())
+}
+
 #[tracing::instrument]
 pub(crate) fn get_kernel_blocking() -> Option<String> {
   if !CONF.system.kernel.enabled {
@@ -63,16 +75,16 @@ pub(crate) fn get_kernel_blocking() -> Option<String> {
   }
 
   let kernel = os_release().ok()?; // this performs a blocking read of /proc/sys/kernel/osrelease
-  if CONF.icons.enabled {
-    match CONF.icons.kind.as_deref() {
-      Some("emoji") => {
+  if icons_enabled() {
+    match CONF.icons.kind {
+      Some(IconKind::Emoji) => {
         if kernel.len() > 41 {
           Some(format!("{}...", kernel.substring(0, 37)))
         } else {
           Some(kernel.trim_end_matches('\n').to_string())
         }
       }
-      Some(&_) | None => {
+      Some(IconKind::Normal) | None => {
         if kernel.len() > 42 {
           Some(format!("{}...", kernel.substring(0, 38)))
         } else {
@@ -94,6 +106,9 @@ pub(crate) fn get_memory() -> Option<String> {
   }
 
   match System::new().memory() {
+    Ok(mem) if CONF.system.mem_usage.bar == Some(true) => Some(calc_bar(
+      saturating_sub_bytes(mem.total, mem.free).as_u64() as f64 / mem.total.as_u64() as f64,
+    )),
     Ok(mem) => match CONF.system.mem_usage.free_before_used {
       Some(false) => Some(format!(
         "{} Used / {} ({} Free)",
@@ -125,6 +140,9 @@ pub(crate) fn get_disk_usage() -> Option<String> {
   }
 
   match System::new().mount_at("/") {
+    Ok(disk) if CONF.system.disk_usage.bar == Some(true) => Some(calc_bar(
+      saturating_sub_bytes(disk.total, disk.free).as_u64() as f64 / disk.total.as_u64() as f64,
+    )),
     Ok(disk) => match CONF.system.disk_usage.free_before_used {
       Some(false) => Some(format!(
         "{} Used / {} ({} Free)",
@@ -149,6 +167,70 @@ pub(crate) fn get_disk_usage() -> Option<String> {
   }
 }
 
+/// The short `used/total` form of memory and disk usage, shown by the compact layout.
+fn used_of_total(total: ByteSize, free: ByteSize) -> String {
+  format!("{}/{}", saturating_sub_bytes(total, free), total)
+}
+
+pub(crate) fn get_memory_compact() -> Option<String> {
+  if !CONF.system.mem_usage.enabled {
+    return None;
+  }
+
+  let mem = System::new().memory().ok()?;
+  Some(used_of_total(mem.total, mem.free))
+}
+
+pub(crate) fn get_disk_compact() -> Option<String> {
+  if !CONF.system.disk_usage.enabled {
+    return None;
+  }
+
+  let disk = System::new().mount_at("/").ok()?;
+  Some(used_of_total(disk.total, disk.free))
+}
+
+pub(crate) fn memory_percent() -> Option<f64> {
+  let mem = System::new().memory().ok()?;
+  Some(
+    saturating_sub_bytes(mem.total, mem.free).as_u64() as f64 / mem.total.as_u64() as f64 * 100.0,
+  )
+}
+
+pub(crate) fn disk_percent() -> Option<f64> {
+  let disk = System::new().mount_at("/").ok()?;
+  Some(
+    saturating_sub_bytes(disk.total, disk.free).as_u64() as f64 / disk.total.as_u64() as f64
+      * 100.0,
+  )
+}
+
+#[tracing::instrument]
+pub(crate) fn get_battery() -> Option<String> {
+  if !CONF.system.battery.enabled {
+    return None;
+  }
+
+  let system = System::new();
+  let battery = system.battery_life().ok()?;
+  if CONF.system.battery.bar == Some(true) {
+    return Some(calc_bar(battery.remaining_capacity as f64));
+  }
+
+  let percent = (battery.remaining_capacity * 100.0).round();
+  match system.on_ac_power() {
+    Ok(true) => Some(format!("{percent}% (charging)")),
+    _ => {
+      let minutes = battery.remaining_time.as_secs() / 60;
+      Some(format!(
+        "{percent}% ({}h {}m left)",
+        minutes / 60,
+        minutes % 60
+      ))
+    }
+  }
+}
+
 #[tracing::instrument]
 pub(crate) fn get_environment() -> Option<String> {
   if !CONF.system.desktop_env.enabled {
@@ -0,0 +1,86 @@
+use crate::{
+  funcs::system_info::{disk_percent, memory_percent},
+  util::statics::CONF,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Level {
+  Ok,
+  Warning,
+  Critical,
+}
+
+impl Level {
+  pub(crate) fn color(self) -> &'static str {
+    match self {
+      Level::Ok => "\x1b[32m",
+      Level::Warning => "\x1b[33m",
+      Level::Critical => "\x1b[31m",
+    }
+  }
+}
+
+/// A module value that crossed one of its configured thresholds.
+pub(crate) struct Alert {
+  pub(crate) level: Level,
+  pub(crate) message: String,
+}
+
+pub(crate) fn level(value: f64, warn: Option<f64>, crit: Option<f64>) -> Level {
+  if crit.is_some_and(|crit| value >= crit) {
+    Level::Critical
+  } else if warn.is_some_and(|warn| value >= warn) {
+    Level::Warning
+  } else {
+    Level::Ok
+  }
+}
+
+fn alert(level: Level, message: String) -> Option<Alert> {
+  match level {
+    Level::Ok => None,
+    level => Some(Alert { level, message }),
+  }
+}
+
+pub(crate) fn memory_alert() -> Option<Alert> {
+  let (warn, crit) = (
+    CONF.system.mem_usage.warn_percent,
+    CONF.system.mem_usage.crit_percent,
+  );
+  if warn.is_none() && crit.is_none() {
+    return None;
+  }
+
+  let percent = memory_percent()?;
+  alert(
+    level(percent, warn, crit),
+    format!("Memory usage is at {percent:.0}%"),
+  )
+}
+
+pub(crate) fn disk_alert() -> Option<Alert> {
+  let (warn, crit) = (
+    CONF.system.disk_usage.warn_percent,
+    CONF.system.disk_usage.crit_percent,
+  );
+  if warn.is_none() && crit.is_none() {
+    return None;
+  }
+
+  let percent = disk_percent()?;
+  alert(
+    level(percent, warn, crit),
+    format!("Disk usage of / is at {percent:.0}%"),
+  )
+}
+
+pub(crate) fn updates_alert(count: i32) -> Option<Alert> {
+  let warn = CONF.packages.update_count.warn.map(f64::from);
+  let crit = CONF.packages.update_count.crit.map(f64::from);
+
+  alert(
+    level(count.into(), warn, crit),
+    format!("{count} updates are pending"),
+  )
+}
@@ -8,21 +8,31 @@ pub mod util;
 use {
   crate::{
     funcs::{
+      check::run_check,
+      config::run_config,
       greet::{get_hostname, greeting},
-      misc::{get_datetime, get_song, get_weather},
-      pkgs::{count_updates, get_package_count},
+      misc::{fetch_weather, get_datetime, get_palette, get_song, get_weather, get_weather_url},
+      pkgs::{count_updates, format_updates, get_package_count},
+      prompt::handle_keypress,
+      render::run_render,
       system_info::{
-        get_disk_usage, get_environment, get_kernel_blocking, get_memory, get_release_blocking,
+        get_battery, get_disk_compact, get_disk_usage, get_environment, get_kernel_blocking,
+        get_memory, get_memory_compact, get_release_blocking, get_release_url_blocking,
       },
+      thresholds::{disk_alert, memory_alert, updates_alert},
     },
     util::{
       formatting::{
-        calc_bottom, calc_whitespace, calc_whitespace_song, calc_with_hostname, upper_first,
+        calc_compact, calc_warnings, compact_modules, pick_icon, render_box, upper_first, Row,
       },
-      statics::{CONF, MISC_EMOJIS, MISC_ICONS, PACKAGE_EMOJIS, PACKAGE_ICONS},
+      render::{render_html, render_markdown},
+      state::should_show_full,
+      statics::{ARGS, CONF, MISC_EMOJIS, MISC_ICONS, PACKAGE_EMOJIS, PACKAGE_ICONS},
+      terminal::{adapt, icons_enabled},
     },
   },
   once_cell::sync::Lazy,
+  openweathermap::CurrentWeather,
   std::{process::exit, time::Instant},
   tracing_subscriber::{
     fmt::{format::FmtSpan, layer},
@@ -33,7 +43,17 @@ use {
 
 #[tokio::main]
 async fn main() {
-  if CONF.util.width < 50 {
+  match ARGS.command.as_str() {
+    "" | "render" => (),
+    "check" => exit(run_check().await),
+    "config" => exit(run_config()),
+    other => {
+      eprintln!("Unknown command: {other}");
+      exit(2);
+    }
+  }
+
+  if CONF.util.width < 50 && !CONF.compact.enabled {
     eprintln!("Width attribute must be at least 50, please change it.");
     exit(1);
   }
@@ -49,6 +69,52 @@ async fn main() {
 
   tracing::info!("Running");
 
+  // Decided before collecting, so modules the compact line doesn't show aren't fetched
+  let compact = ARGS.command.is_empty()
+    && matches!(ARGS.output.as_str(), "" | "terminal")
+    && (CONF.compact.enabled || !should_show_full());
+  let (hostname, rows, weather) = collect(compact).await;
+
+  if ARGS.command == "render" {
+    exit(run_render(&render_box(hostname, &rows)));
+  }
+
+  match ARGS.output.as_str() {
+    "" | "terminal" => (),
+    "html" => {
+      print!("{}", render_html(&render_box(hostname, &rows)));
+      return;
+    }
+    "markdown" => {
+      print!("{}", render_markdown(hostname.as_deref(), &rows));
+      return;
+    }
+    other => {
+      eprintln!("Unknown output format: {other}");
+      exit(2);
+    }
+  }
+
+  if compact {
+    println!("{}", adapt(&calc_compact(hostname.as_deref(), &rows)));
+    return;
+  }
+
+  for line in render_box(hostname, &rows) {
+    println!("{}", adapt(&line));
+  }
+  if CONF.util.warning_summary == Some(true) {
+    for line in calc_warnings(&rows) {
+      println!("{}", adapt(&line));
+    }
+  }
+
+  handle_keypress(weather.as_ref()).await;
+}
+
+/// Gathers the hostname and every module, running the slow ones concurrently. The
+/// fetched weather is returned as well for the detailed view of the prompt.
+async fn collect(compact: bool) -> (Option<String>, Vec<Row>, Option<CurrentWeather>) {
   let time = Instant::now();
 
   Lazy::force(&CONF);
@@ -59,16 +125,24 @@ async fn main() {
   let datetime = get_datetime();
   let memory = get_memory();
   let disk = get_disk_usage();
+  let battery = get_battery();
   let environment = get_environment();
 
   // These are proper async functions
-  let weather = tokio::spawn(get_weather());
+  let weather = tokio::spawn(async move {
+    if compact && !compact_modules().contains(&"weather") {
+      None
+    } else {
+      fetch_weather().await
+    }
+  });
   let up_count = tokio::spawn(count_updates());
   let package_count = tokio::spawn(get_package_count());
 
   // These are functions that block
   let song = tokio::task::spawn_blocking(get_song);
   let release = tokio::task::spawn_blocking(get_release_blocking);
+  let release_url = tokio::task::spawn_blocking(get_release_url_blocking);
   let kernel = tokio::task::spawn_blocking(get_kernel_blocking);
 
   let weather = weather.await.unwrap();
@@ -77,6 +151,7 @@ async fn main() {
 
   let song = song.await.unwrap();
   let release = release.await.unwrap();
+  let release_url = release_url.await.unwrap();
   let kernel = kernel.await.unwrap();
 
   tracing::info!(
@@ -84,218 +159,56 @@ async fn main() {
     time.elapsed().as_secs_f32()
   );
 
-  if let Some(hostname) = hostname && let Some(greeting) = greeting && let Some(datetime) = datetime && let Some(weather) = weather {
-        println!(
-            "{}",
-            calc_with_hostname(format!("╭─── \x1b[32m{hostname}\x1b[0m "))
-        );
-        println!("{}", calc_whitespace(format!("│ {greeting}!")));
-        println!("{}", calc_whitespace(datetime));
-        println!("{}", calc_whitespace(weather));
-    }
-
-  if CONF.icons.enabled {
-    match CONF.icons.kind.as_deref() {
-            Some("emoji") => {
-                if let Some(release) = release && let Some(kernel) = kernel && let Some(memory) = memory && let Some(disk) = disk && let Some(environment) = environment {
-                    println!(
-                        "{}",
-                        calc_whitespace(format!("│ {} {release}", MISC_EMOJIS[0]))
-                    );
-                    println!(
-                        "{}",
-                        calc_whitespace(format!("│ {} {kernel}", MISC_EMOJIS[1]))
-                    );
-                    println!(
-                        "{}",
-                        calc_whitespace(format!("│ {} {memory}", MISC_EMOJIS[2]))
-                    );
-                    println!(
-                        "{}",
-                        calc_whitespace(format!("│ {} {disk}", MISC_EMOJIS[3]))
-                    );
-                    println!(
-                        "{}",
-                        calc_whitespace(format!(
-                            "│ {} {}",
-                            MISC_EMOJIS[4],
-                            upper_first(environment)
-                        ))
-                    );
-                    }
-            }
-            Some("normal") => {
-                if let Some(release) = release {
-                    println!(
-                        "{}",
-                        calc_whitespace(format!("│ {} {}", MISC_ICONS[0], release))
-                    );
-                }
-                if let Some(kernel) = kernel {
-                    println!(
-                        "{}",
-                        calc_whitespace(format!("│ {} {}", MISC_ICONS[1], kernel))
-                    );
-                }
-                if let Some(memory) = memory {
-                    println!(
-                        "{}",
-                        calc_whitespace(format!("│ {} {}", MISC_ICONS[2], memory))
-                    );
-                }
-                if let Some(disk) = disk {
-                    println!(
-                        "{}",
-                        calc_whitespace(format!("│ {} {}", MISC_ICONS[3], disk))
-                    );
-                }
-
-                if let Some(environment) = environment {
-                    println!(
-                        "{}",
-                        calc_whitespace(format!(
-                            "│ {} {}",
-                            MISC_ICONS[4],
-                            upper_first(environment)
-                        ))
-                    );
-                }
-            }
-            Some(&_) | None => {
-                if let Some(release) = release {
-                    println!("{}", calc_whitespace(format!("│ {release}")));
-                }
-                if let Some(kernel) = kernel {
-                    println!("{}", calc_whitespace(format!("│ {kernel}")));
-                }
-                if let Some(memory) = memory {
-                    println!("{}", calc_whitespace(format!("│ {memory}")));
-                }
-                if let Some(disk) = disk {
-                    println!("{}", calc_whitespace(format!("│ {disk}")));
-                }
-                if let Some(environment) = environment {
-                    println!(
-                        "{}",
-                        calc_whitespace(format!("│ {}", upper_first(environment)))
-                    );
-                }
-            }
-        }
-  } else {
-    if let Some(release) = release {
-      println!("{}", calc_whitespace(format!("│ {release}")));
-    }
-    if let Some(kernel) = kernel {
-      println!("{}", calc_whitespace(format!("│ {kernel}")));
-    }
-    if let Some(memory) = memory {
-      println!("{}", calc_whitespace(format!("│ {memory}")));
-    }
-    if let Some(disk) = disk {
-      println!("{}", calc_whitespace(format!("│ {disk}")));
-    }
-
-    if let Some(environment) = environment {
-      println!(
-        "{}",
-        calc_whitespace(format!("│ {}", upper_first(environment)))
-      );
-    }
-  }
-
-  if let Some(count) = up_count {
-    println!("{}", calc_whitespace(count));
-  }
-
-  if CONF.icons.enabled {
-    match CONF.icons.kind.as_deref() {
-      Some("emoji") => match package_count {
-        None => (),
-        Some(0) => println!(
-          "{}",
-          calc_whitespace(format!("│ {} No packages", PACKAGE_EMOJIS[12]))
-        ),
-        Some(1) => println!(
-          "{}",
-          calc_whitespace(format!("│ {} 1 package", PACKAGE_EMOJIS[12]))
-        ),
-        Some(n) => println!(
-          "{}",
-          calc_whitespace(format!("│ {} {} packages", PACKAGE_EMOJIS[12], n))
-        ),
-      },
-      Some("normal") => match package_count {
-        None => (),
-        Some(0) => println!(
-          "{}",
-          calc_whitespace(format!("│ {} No packages", PACKAGE_ICONS[11]))
-        ),
-        Some(1) => println!(
-          "{}",
-          calc_whitespace(format!("│ {} 1 package", PACKAGE_ICONS[11]))
-        ),
-        Some(n) => println!(
-          "{}",
-          calc_whitespace(format!("│ {} {} packages", PACKAGE_ICONS[11], n))
-        ),
-      },
-      Some(&_) | None => match package_count {
-        None => (),
-        Some(0) => println!("{}", calc_whitespace("│ No packages".to_string())),
-        Some(1) => println!("{}", calc_whitespace("│ 1 package".to_string())),
-        Some(n) => println!("{}", calc_whitespace(format!("│ {n} packages"))),
-      },
-    }
-  } else {
-    match package_count {
-      None => (),
-      Some(0) => println!("{}", calc_whitespace("│ No packages".to_string())),
-      Some(1) => println!("{}", calc_whitespace("│ 1 package".to_string())),
-      Some(n) => println!("{}", calc_whitespace(format!("│ {n} packages"))),
-    }
-  }
+  let cjk_regex =
+    Regex::new(r"[\p{Han}\p{Hiragana}\p{Katakana}\p{Hangul}\p{Bopomofo}\p{Yi}]+").unwrap();
+  let (song, song_url) = song.map_or((None, None), |(song, url)| (Some(song), url));
+  let song =
+    song.filter(|song| !(icons_enabled() && CONF.icons.kind.is_some() && cjk_regex.is_match(song)));
+
+  let mut palette = get_palette().into_iter();
+
+  let rows = vec![
+    Row::new(
+      "greeting",
+      "",
+      greeting.map(|greeting| format!("{greeting}!")),
+    ),
+    Row::from_pair("datetime", datetime),
+    Row::from_pair("weather", weather.as_ref().and_then(get_weather)).with_link(get_weather_url()),
+    Row::new("release", pick_icon(MISC_EMOJIS[0], MISC_ICONS[0]), release).with_link(release_url),
+    Row::new("kernel", pick_icon(MISC_EMOJIS[1], MISC_ICONS[1]), kernel),
+    Row::new("memory", pick_icon(MISC_EMOJIS[2], MISC_ICONS[2]), memory)
+      .with_alert(memory_alert())
+      .with_compact(get_memory_compact()),
+    Row::new("disk", pick_icon(MISC_EMOJIS[3], MISC_ICONS[3]), disk)
+      .with_alert(disk_alert())
+      .with_compact(get_disk_compact()),
+    Row::new("battery", pick_icon(MISC_EMOJIS[6], MISC_ICONS[6]), battery),
+    Row::new(
+      "environment",
+      pick_icon(MISC_EMOJIS[4], MISC_ICONS[4]),
+      environment.map(upper_first),
+    ),
+    Row::from_pair("updates", up_count.map(format_updates))
+      .with_alert(up_count.and_then(updates_alert)),
+    Row::new(
+      "packages",
+      pick_icon(PACKAGE_EMOJIS[12], PACKAGE_ICONS[11]),
+      package_count.map(|count| match count {
+        0 => "No packages".to_string(),
+        1 => "1 package".to_string(),
+        n => format!("{n} packages"),
+      }),
+    ),
+    Row::new(
+      "song",
+      pick_icon(MISC_EMOJIS[5], MISC_ICONS[5]),
+      song.map(|song| song.trim_matches('\n').to_string()),
+    )
+    .with_link(song_url),
+    Row::new("palette", "", palette.next()),
+    Row::new("palette_bright", "", palette.next()),
+  ];
 
-  if CONF.icons.enabled {
-    match CONF.icons.kind.as_deref() {
-      Some("emoji") => {
-        if let Some(song) = song.as_ref() {
-          let cjk_regex =
-            Regex::new(r"[\p{Han}\p{Hiragana}\p{Katakana}\p{Hangul}\p{Bopomofo}\p{Yi}]+").unwrap();
-          if !cjk_regex.is_match(song) {
-            println!(
-              "{}",
-              calc_whitespace_song(format!("│ {} {}", MISC_EMOJIS[5], song.trim_matches('\n')))
-            );
-          }
-        }
-      }
-      Some("normal") => {
-        if let Some(song) = song.as_ref() {
-          let cjk_regex =
-            Regex::new(r"[\p{Han}\p{Hiragana}\p{Katakana}\p{Hangul}\p{Bopomofo}\p{Yi}]+").unwrap();
-          if !cjk_regex.is_match(song) {
-            println!(
-              "{}",
-              calc_whitespace_song(format!("│ {} {}", MISC_ICONS[5], song.trim_matches('\n')))
-            );
-          }
-        }
-      }
-      Some(&_) | None => {
-        if let Some(song) = song.as_ref() {
-          println!(
-            "{}",
-            calc_whitespace_song(format!("│ {}", song.trim_matches('\n')))
-          );
-        }
-      }
-    }
-  } else if let Some(song) = song.as_ref() {
-    println!(
-      "{}",
-      calc_whitespace_song(format!("│ {}", song.trim_matches('\n')))
-    );
-  }
-  println!("{}", calc_bottom());
+  (hostname, rows, weather)
 }
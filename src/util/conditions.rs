@@ -0,0 +1,204 @@
+use {
+  crate::util::{
+    config::merge,
+    diagnostics::{validate, Source},
+  },
+  std::env,
+  sys_info::hostname,
+  toml::{map::Map, Value},
+};
+
+/// Matches `text` against a pattern where `*` is any run of characters and `?` any one.
+fn glob_match(pattern: &str, text: &str) -> bool {
+  let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+  let text: Vec<char> = text.to_lowercase().chars().collect();
+  let (mut p, mut t) = (0, 0);
+  let mut backtrack = None;
+
+  while t < text.len() {
+    match pattern.get(p) {
+      Some('*') => {
+        backtrack = Some((p, t));
+        p += 1;
+      }
+      Some(c) if *c == '?' || *c == text[t] => {
+        p += 1;
+        t += 1;
+      }
+      _ => match backtrack {
+        Some((star, matched)) => {
+          p = star + 1;
+          t = matched + 1;
+          backtrack = Some((star, matched + 1));
+        }
+        None => return false,
+      },
+    }
+  }
+
+  pattern[p..].iter().all(|c| *c == '*')
+}
+
+fn env_set(name: &str) -> bool {
+  env::var_os(name).is_some_and(|value| !value.is_empty())
+}
+
+fn in_ssh() -> bool {
+  env_set("SSH_CONNECTION") || env_set("SSH_CLIENT") || env_set("SSH_TTY")
+}
+
+fn session_kind() -> &'static str {
+  match env::var("XDG_SESSION_TYPE").as_deref() {
+    Ok("x11" | "wayland" | "mir") => "graphical",
+    Ok("tty") => "tty",
+    _ if env_set("WAYLAND_DISPLAY") || env_set("DISPLAY") => "graphical",
+    _ => "tty",
+  }
+}
+
+/// The keys of a `[[when]]` block that are conditions rather than overrides.
+const CONDITIONS: [&str; 4] = ["hostname", "ssh", "session", "desktop"];
+
+/// Checks one condition of a `[[when]]` block, returning `None` if `key` isn't a
+/// condition and belongs to the overrides instead.
+fn condition(key: &str, value: &Value) -> Option<bool> {
+  let matches = match (key, value) {
+    ("hostname", Value::String(pattern)) => glob_match(pattern, &hostname().unwrap_or_default()),
+    ("ssh", Value::Boolean(ssh)) => in_ssh() == *ssh,
+    ("session", Value::String(kind)) => session_kind() == kind,
+    ("desktop", Value::String(pattern)) => env::var("XDG_CURRENT_DESKTOP")
+      .unwrap_or_default()
+      .split(':')
+      .any(|desktop| glob_match(pattern, desktop)),
+    (key, value) if CONDITIONS.contains(&key) => {
+      tracing::warn!("Invalid value for the {} condition: {}", key, value);
+      false
+    }
+    _ => return None,
+  };
+
+  Some(matches)
+}
+
+/// Checks the overrides of every `[[when]]` block in a layer like a config file of its
+/// own, so a typo in one is reported against the file it's written in.
+pub(crate) fn check_conditions(source: &Source, layer: &mut Value) {
+  let Some(table) = layer.as_table_mut() else {
+    return;
+  };
+  // A single block may be written without the list, like a KDL `when` node
+  if let Some(block) = table.get("when").filter(|block| block.is_table()).cloned() {
+    table.insert("when".to_string(), Value::Array(vec![block]));
+  }
+
+  let Some(Value::Array(blocks)) = table.get_mut("when") else {
+    return;
+  };
+
+  for (i, block) in blocks.iter_mut().enumerate() {
+    let prefix = ["when".to_string(), i.to_string()];
+    let Value::Table(table) = block else {
+      validate(source, block, &prefix);
+      continue;
+    };
+
+    let (conditions, overrides): (Map<String, Value>, Map<String, Value>) = std::mem::take(table)
+      .into_iter()
+      .partition(|(key, _)| CONDITIONS.contains(&key.as_str()));
+    let mut overrides = Value::Table(overrides);
+    validate(source, &mut overrides, &prefix);
+
+    *table = conditions;
+    if let Value::Table(overrides) = overrides {
+      table.extend(overrides);
+    }
+  }
+}
+
+/// Merges the overrides of every `[[when]]` block whose conditions all hold, in the
+/// order they're written.
+pub(crate) fn apply_conditions(merged: &mut Value) {
+  let blocks = merged.as_table_mut().and_then(|table| table.remove("when"));
+  let Some(Value::Array(blocks)) = blocks else {
+    return;
+  };
+
+  for block in blocks {
+    let Value::Table(block) = block else {
+      continue;
+    };
+
+    let mut overrides = Map::new();
+    let mut applies = true;
+    for (key, value) in block {
+      match condition(&key, &value) {
+        Some(matches) => applies &= matches,
+        None => {
+          overrides.insert(key, value);
+        }
+      }
+    }
+
+    if applies {
+      merge(merged, Value::Table(overrides));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use {super::*, crate::util::diagnostics::locate_toml};
+
+  fn toml(text: &str) -> Value {
+    toml::from_str(text).unwrap()
+  }
+
+  #[test]
+  fn globs_match_case_insensitively() {
+    assert!(glob_match("work-*", "WORK-laptop"));
+    assert!(glob_match("h?st", "host"));
+    assert!(glob_match("*", ""));
+    assert!(!glob_match("work-*", "home"));
+    assert!(!glob_match("h?st", "hoost"));
+  }
+
+  #[test]
+  fn matching_blocks_are_merged_in_order() {
+    let mut merged = toml(
+      r#"
+      greeting.enabled = false
+
+      [[when]]
+      hostname = "*"
+      greeting.enabled = true
+
+      [[when]]
+      hostname = "*"
+      time.enabled = true
+
+      [[when]]
+      hostname = "no such host"
+      greeting.enabled = false
+      "#,
+    );
+    apply_conditions(&mut merged);
+    assert_eq!(merged, toml("greeting.enabled = true\ntime.enabled = true"));
+  }
+
+  #[test]
+  fn blocks_are_checked_without_their_conditions() {
+    let content = "[[when]]\nssh = true\n\n[[when]]\nhostname = \"work-*\"\ntime.enabld = true\n";
+    let mut layer = toml(content);
+    let locate = |path: &[String]| locate_toml(content, path);
+    let source = Source {
+      name: "config.toml",
+      content,
+      locate: &locate,
+    };
+    check_conditions(&source, &mut layer);
+    assert_eq!(
+      layer,
+      toml("[[when]]\nssh = true\n\n[[when]]\nhostname = \"work-*\"\ntime = {}")
+    );
+  }
+}
@@ -1,7 +1,30 @@
-use serde::Deserialize;
+use {
+  schemars::JsonSchema,
+  serde::{
+    de::{
+      self,
+      value::{MapAccessDeserializer, SeqAccessDeserializer},
+      IntoDeserializer, MapAccess, SeqAccess, Visitor,
+    },
+    Deserialize, Deserializer, Serialize,
+  },
+  std::{collections::HashMap, fmt, marker::PhantomData},
+};
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
 pub(crate) struct Config {
+  // These are resolved while the config files are merged, they're only here so they
+  // pass deny_unknown_fields
+  #[serde(skip_serializing, deserialize_with = "one_or_many")]
+  #[schemars(with = "OneOrMany<String>")]
+  pub(crate) include: Vec<String>,
+  #[serde(skip_serializing)]
+  #[schemars(with = "HashMap<String, Config>")]
+  pub(crate) profiles: HashMap<String, toml::Value>,
+  #[serde(skip_serializing, deserialize_with = "one_or_many")]
+  #[schemars(with = "OneOrMany<HashMap<String, serde_json::Value>>")]
+  pub(crate) when: Vec<toml::Value>,
   pub(crate) main: Main,
   pub(crate) util: Util,
   pub(crate) greeting: Greeting,
@@ -11,105 +34,437 @@ pub(crate) struct Config {
   pub(crate) system: System,
   pub(crate) packages: Packages,
   pub(crate) song: Song,
+  pub(crate) prompt: Prompt,
+  pub(crate) compact: Compact,
+  #[serde(deserialize_with = "one_or_many")]
+  #[schemars(with = "OneOrMany<Section>")]
+  pub(crate) sections: Vec<Section>,
+  pub(crate) layout: Layout,
+  pub(crate) labels: Labels,
+  pub(crate) bars: Bars,
+  pub(crate) check: Check,
+  pub(crate) palette: Palette,
+  pub(crate) format: HashMap<String, String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
 pub(crate) struct Main {
   pub(crate) hostname: Option<String>,
   pub(crate) name: Option<String>,
+  pub(crate) show: Option<ShowPolicy>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ShowPolicy {
+  Always,
+  OncePerDay,
+  OncePerBoot,
+  OncePerSession,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
 pub(crate) struct Util {
   pub(crate) width: i32,
+  pub(crate) warning_summary: Option<bool>,
+  pub(crate) color: Option<When>,
+  pub(crate) hyperlinks: Option<When>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum When {
+  Auto,
+  Always,
+  Never,
+}
+
+impl Default for Util {
+  fn default() -> Self {
+    Self {
+      width: 55,
+      warning_summary: None,
+      color: None,
+      hyperlinks: None,
+    }
+  }
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
 pub(crate) struct Greeting {
   pub(crate) enabled: bool,
 }
 
-#[derive(Deserialize)]
+impl Default for Greeting {
+  fn default() -> Self {
+    Self { enabled: true }
+  }
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
 pub(crate) struct Icons {
   pub(crate) enabled: bool,
-  pub(crate) kind: Option<String>,
+  pub(crate) kind: Option<IconKind>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum IconKind {
+  Normal,
+  Emoji,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
 pub(crate) struct Time {
   pub(crate) enabled: bool,
-  pub(crate) kind: Option<String>,
+  pub(crate) kind: Option<TimeFormat>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TimeFormat {
+  #[serde(rename = "12h")]
+  TwelveHour,
+  #[serde(rename = "24h")]
+  TwentyFourHour,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
 pub(crate) struct Weather {
   pub(crate) enabled: bool,
   pub(crate) values: WeatherValues,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
 pub(crate) struct WeatherValues {
   pub(crate) api_key: Option<String>,
   pub(crate) location: Option<String>,
   pub(crate) lang: Option<String>,
-  pub(crate) units: Option<String>,
+  pub(crate) units: Option<Units>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Units {
+  Standard,
+  Metric,
+  Imperial,
 }
 
-#[derive(Deserialize)]
+impl Units {
+  /// The name OpenWeatherMap expects for these units.
+  pub(crate) fn as_str(self) -> &'static str {
+    match self {
+      Self::Standard => "standard",
+      Self::Metric => "metric",
+      Self::Imperial => "imperial",
+    }
+  }
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
 pub(crate) struct System {
   pub(crate) release: Release,
   pub(crate) kernel: Kernel,
   pub(crate) mem_usage: MemUsage,
   pub(crate) disk_usage: DiskUsage,
+  pub(crate) battery: Battery,
   pub(crate) desktop_env: DesktopEnv,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
 pub(crate) struct Release {
   pub(crate) enabled: bool,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
 pub(crate) struct Kernel {
   pub(crate) enabled: bool,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
 pub(crate) struct MemUsage {
   pub(crate) enabled: bool,
   pub(crate) free_before_used: Option<bool>,
+  pub(crate) bar: Option<bool>,
+  pub(crate) warn_percent: Option<f64>,
+  pub(crate) crit_percent: Option<f64>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
 pub(crate) struct DiskUsage {
   pub(crate) enabled: bool,
   pub(crate) free_before_used: Option<bool>,
+  pub(crate) bar: Option<bool>,
+  pub(crate) warn_percent: Option<f64>,
+  pub(crate) crit_percent: Option<f64>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Battery {
+  pub(crate) enabled: bool,
+  pub(crate) bar: Option<bool>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
 pub(crate) struct DesktopEnv {
   pub(crate) enabled: bool,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
 pub(crate) struct Packages {
-  pub(crate) package_managers: Option<toml::Value>,
+  #[serde(deserialize_with = "one_or_many")]
+  #[schemars(with = "OneOrMany<PackageManager>")]
+  pub(crate) package_managers: Vec<PackageManager>,
+  pub(crate) upgrade_command: Option<String>,
   pub(crate) package_count: PackageCount,
   pub(crate) update_count: UpdateCount,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum PackageManager {
+  Pacman,
+  Apt,
+  Xbps,
+  Portage,
+  Apk,
+  Dnf,
+  NixUser,
+  NixSystem,
+}
+
+// How the schema describes a field read with `one_or_many`
+#[derive(JsonSchema)]
+#[serde(untagged)]
+#[allow(dead_code)]
+enum OneOrMany<T> {
+  One(T),
+  Many(Vec<T>),
+}
+
+/// Accepts a single item as well as a list, `package_managers = "pacman"` predates lists
+/// and a KDL node that isn't repeated reads as a single item.
+fn one_or_many<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+  deserializer: D,
+) -> Result<Vec<T>, D::Error> {
+  struct OneOrMany<T>(PhantomData<T>);
+
+  impl<'de, T: Deserialize<'de>> Visitor<'de> for OneOrMany<T> {
+    type Value = Vec<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      f.write_str("a value or a list of them")
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<Self::Value, E> {
+      T::deserialize(value.into_deserializer()).map(|item| vec![item])
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+      T::deserialize(value.into_deserializer()).map(|item| vec![item])
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Self::Value, E> {
+      T::deserialize(value.into_deserializer()).map(|item| vec![item])
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+      T::deserialize(value.into_deserializer()).map(|item| vec![item])
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+      T::deserialize(MapAccessDeserializer::new(map)).map(|item| vec![item])
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+      Vec::deserialize(SeqAccessDeserializer::new(seq))
+    }
+  }
+
+  deserializer.deserialize_any(OneOrMany(PhantomData))
+}
+
+fn optional_one_or_many<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+  deserializer: D,
+) -> Result<Option<Vec<T>>, D::Error> {
+  one_or_many(deserializer).map(Some)
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
 pub(crate) struct PackageCount {
   pub(crate) enabled: bool,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
 pub(crate) struct UpdateCount {
   pub(crate) enabled: bool,
+  pub(crate) warn: Option<i32>,
+  pub(crate) crit: Option<i32>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
 pub(crate) struct Song {
   pub(crate) enabled: bool,
-  pub(crate) mode: Option<String>,
+  pub(crate) mode: Option<SongMode>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SongMode {
+  Mpris,
+  Playerctl,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Prompt {
+  pub(crate) enabled: bool,
+  pub(crate) timeout: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Compact {
+  pub(crate) enabled: bool,
+  #[serde(deserialize_with = "optional_one_or_many")]
+  #[schemars(with = "Option<OneOrMany<String>>")]
+  pub(crate) modules: Option<Vec<String>>,
+  pub(crate) separator: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Section {
+  pub(crate) title: Option<String>,
+  #[serde(deserialize_with = "one_or_many")]
+  #[schemars(with = "OneOrMany<String>")]
+  pub(crate) modules: Vec<String>,
+  pub(crate) collapse_empty: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Layout {
+  pub(crate) columns: Option<usize>,
+  pub(crate) style: Option<LayoutStyle>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum LayoutStyle {
+  Shared,
+  Separate,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Labels {
+  pub(crate) enabled: bool,
+  pub(crate) position: Option<LabelPosition>,
+  pub(crate) text: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum LabelPosition {
+  Before,
+  After,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Bars {
+  pub(crate) length: Option<usize>,
+  pub(crate) filled: Option<String>,
+  pub(crate) empty: Option<String>,
+  #[serde(deserialize_with = "one_or_many")]
+  #[schemars(with = "OneOrMany<String>")]
+  pub(crate) gradient: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Check {
+  pub(crate) failed_services_warn: Option<i32>,
+  pub(crate) failed_services_crit: Option<i32>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Palette {
+  pub(crate) enabled: bool,
+  pub(crate) block: Option<String>,
+  pub(crate) layout: Option<PaletteLayout>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PaletteLayout {
+  Split,
+  Single,
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn packages(text: &str) -> Vec<PackageManager> {
+    toml::from_str::<Packages>(text).unwrap().package_managers
+  }
+
+  #[test]
+  fn package_managers_take_one_or_many() {
+    assert_eq!(
+      packages("package_managers = \"apt\""),
+      [PackageManager::Apt]
+    );
+    assert_eq!(
+      packages("package_managers = [\"pacman\", \"nix-user\"]"),
+      [PackageManager::Pacman, PackageManager::NixUser]
+    );
+    assert!(toml::from_str::<Packages>("package_managers = \"yum\"").is_err());
+  }
+
+  #[test]
+  fn list_fields_take_a_single_item() {
+    let config: Config =
+      toml::from_str("include = \"theme.toml\"\n[sections]\ntitle = \"a\"\nmodules = \"release\"")
+        .unwrap();
+    assert_eq!(config.include, ["theme.toml"]);
+    assert_eq!(config.sections.len(), 1);
+    assert_eq!(config.sections[0].modules, ["release"]);
+  }
+
+  #[test]
+  fn option_typos_are_refused() {
+    let config: Config = toml::from_str(
+      "main.show = \"once_per_day\"\nutil.color = \"never\"\nlabels.position = \"after\"",
+    )
+    .unwrap();
+    assert_eq!(config.main.show, Some(ShowPolicy::OncePerDay));
+    assert_eq!(config.util.color, Some(When::Never));
+    assert_eq!(config.labels.position, Some(LabelPosition::After));
+
+    assert!(toml::from_str::<Config>("labels.position = \"rigth\"").is_err());
+    assert!(toml::from_str::<Config>("layout.style = \"seperate\"").is_err());
+    assert!(toml::from_str::<Config>("util.hyperlinks = \"yes\"").is_err());
+  }
 }
@@ -1,22 +1,88 @@
 use {
-  crate::util::conf_structs::Config,
-  argparse::{ArgumentParser, Store, StoreTrue},
-  std::{env, io::ErrorKind},
+  crate::util::{
+    conditions::apply_conditions,
+    conf_structs::Config,
+    diagnostics::{validate, warning, Source},
+    formats::{parse_config, EXTENSIONS},
+    statics::ARGS,
+  },
+  argparse::{ArgumentParser, Collect, List, Store, StoreTrue},
+  std::{
+    env,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process::exit,
+  },
+  sys_info::hostname,
+  toml::{map::Map, Value},
 };
 
-pub(crate) fn read_config() -> Config {
-  let mut path = format!("{}/.config/draconis/config.toml", env::var("HOME").unwrap());
+pub(crate) struct Args {
+  pub(crate) config: String,
+  pub(crate) compact: bool,
+  pub(crate) color: String,
+  pub(crate) output: String,
+  pub(crate) set: Vec<String>,
+  pub(crate) profile: String,
+  pub(crate) command: String,
+  pub(crate) arguments: Vec<String>,
+}
+
+pub(crate) fn parse_args() -> Args {
+  let mut args = Args {
+    config: String::new(),
+    compact: false,
+    color: String::new(),
+    output: String::new(),
+    set: Vec::new(),
+    profile: String::new(),
+    command: String::new(),
+    arguments: Vec::new(),
+  };
   let mut ver = false;
   {
     let mut ap = ArgumentParser::new();
     ap.set_description("A simple greeter for your terminal, made in Rust");
-    ap.refer(&mut path).add_option(
+    ap.refer(&mut args.config).add_option(
       &["-c", "--config"],
       Store,
-      "Specify a path to a config file",
+      "Use this config file instead of the one in $XDG_CONFIG_HOME",
     );
     ap.refer(&mut ver)
       .add_option(&["-v", "--version"], StoreTrue, "View program version");
+    ap.refer(&mut args.compact).add_option(
+      &["--compact"],
+      StoreTrue,
+      "Print the enabled modules on a single line",
+    );
+    ap.refer(&mut args.color).add_option(
+      &["--color"],
+      Store,
+      "When to use colors: always, auto or never",
+    );
+    ap.refer(&mut args.output).add_option(
+      &["--output"],
+      Store,
+      "Print as terminal (default), html or markdown",
+    );
+    ap.refer(&mut args.set).add_option(
+      &["--set"],
+      Collect,
+      "Override a config key, like --set weather.location=Berlin",
+    );
+    ap.refer(&mut args.profile).add_option(
+      &["--profile"],
+      Store,
+      "Apply a [profiles.<name>] block, defaults to the one named after the hostname",
+    );
+    ap.refer(&mut args.command).add_argument(
+      "command",
+      Store,
+      r#"Command to run instead of the greeter ("check", "render" or "config")"#,
+    );
+    ap.refer(&mut args.arguments)
+      .add_argument("arguments", List, "Arguments for the command");
+    ap.stop_on_first_argument(true);
     ap.parse_args_or_exit();
   }
 
@@ -25,115 +91,468 @@ pub(crate) fn read_config() -> Config {
     std::process::exit(0);
   }
 
-  let content = match std::fs::read_to_string(path) {
-    Err(e) if e.kind() == ErrorKind::NotFound => {
-      return toml::from_str(
-        r#"
-                [main]
+  args
+}
 
-                [util]
+fn config_home() -> Option<PathBuf> {
+  match env::var("XDG_CONFIG_HOME") {
+    Ok(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+    _ => Some(PathBuf::from(env::var("HOME").ok()?).join(".config")),
+  }
+}
 
-                [greeting]
-                enabled = true
+/// Picks the config file in `dir` by the first extension that exists, falling back to
+/// `config.toml`.
+fn find_config(dir: PathBuf) -> PathBuf {
+  EXTENSIONS
+    .iter()
+    .map(|extension| dir.join(format!("config.{extension}")))
+    .find(|path| path.exists())
+    .unwrap_or_else(|| dir.join("config.toml"))
+}
 
-                [icons]
-                enabled = false
+/// The other `config.*` files next to `path`, which aren't read because `path` is found
+/// first.
+pub(crate) fn shadowed_configs(path: &Path) -> Vec<PathBuf> {
+  let Some(dir) = path.parent() else {
+    return Vec::new();
+  };
+  EXTENSIONS
+    .iter()
+    .map(|extension| dir.join(format!("config.{extension}")))
+    .filter(|other| other != path && other.exists())
+    .collect()
+}
 
-                [time]
-                enabled = false
+/// The config file of the user, `--config` or the one in `$XDG_CONFIG_HOME`.
+pub(crate) fn user_config_path() -> Option<PathBuf> {
+  if ARGS.config.is_empty() {
+    config_home().map(|dir| find_config(dir.join("draconis")))
+  } else {
+    Some(PathBuf::from(&ARGS.config))
+  }
+}
 
-                [weather]
-                enabled = false
+/// Lists the config files from lowest to highest priority: the system-wide config,
+/// then `$XDG_CONFIG_DIRS` (earlier entries win), then the user's own config.
+pub(crate) fn config_paths() -> Vec<PathBuf> {
+  let mut paths = vec![find_config(PathBuf::from("/etc/draconis"))];
+
+  let dirs = env::var("XDG_CONFIG_DIRS")
+    .ok()
+    .filter(|dirs| !dirs.is_empty())
+    .unwrap_or_else(|| "/etc/xdg".to_string());
+  let mut dirs: Vec<PathBuf> = dirs
+    .split(':')
+    .filter(|dir| !dir.is_empty())
+    .map(|dir| find_config(PathBuf::from(dir).join("draconis")))
+    .collect();
+  dirs.reverse();
+  paths.extend(dirs);
+
+  if ARGS.config.is_empty() {
+    paths.extend(config_home().map(|dir| find_config(dir.join("draconis"))));
+  } else {
+    paths.push(PathBuf::from(&ARGS.config));
+  }
 
-                [weather.values]
+  paths
+}
 
-                [system]
+/// Merges `layer` into `base`, recursing into tables so a layer only replaces the keys
+/// it sets.
+pub(crate) fn merge(base: &mut Value, layer: Value) {
+  match (base, layer) {
+    (Value::Table(base), Value::Table(layer)) => {
+      for (key, value) in layer {
+        match base.get_mut(&key) {
+          Some(existing) => merge(existing, value),
+          None => {
+            base.insert(key, value);
+          }
+        }
+      }
+    }
+    (base, layer) => *base = layer,
+  }
+}
 
-                [system.release]
-                enabled = false
+/// Merges a whole config file into `base`. Arrays are replaced like any other value,
+/// except `when`: its blocks are appended so each file can add its own conditions.
+fn merge_layer(base: &mut Value, mut layer: Value) {
+  let when = layer.as_table_mut().and_then(|table| table.remove("when"));
+  merge(base, layer);
+  let Some(when) = when else { return };
+  let Value::Table(base) = base else { return };
+  match (base.get_mut("when"), when) {
+    (Some(Value::Array(existing)), Value::Array(when)) => existing.extend(when),
+    (_, when) => {
+      base.insert("when".to_string(), when);
+    }
+  }
+}
 
-                [system.kernel]
-                enabled = false
+fn expand_path(path: &str, relative_to: &Path) -> PathBuf {
+  match path.strip_prefix("~/") {
+    Some(rest) => PathBuf::from(env::var("HOME").unwrap_or_default()).join(rest),
+    None => relative_to.join(path),
+  }
+}
 
-                [system.mem_usage]
-                enabled = false
+/// Reads one config file, reporting it as a whole so errors point into the right file.
+/// Files listed in its `include` are read first, so the including file wins.
+fn read_layer(path: &Path, required: bool, depth: usize) -> Option<Value> {
+  let name = path.display().to_string();
+  let content = match std::fs::read_to_string(path) {
+    Err(e) if e.kind() == ErrorKind::NotFound && !required => return None,
+    Err(e) => {
+      eprintln!("error: could not read {name}: {e}");
+      exit(1);
+    }
+    Ok(content) => content,
+  };
 
-                [system.disk_usage]
-                enabled = false
+  let mut layer = parse_config(&name, &content);
 
-                [system.desktop_env]
-                enabled = false
+  let includes = layer
+    .as_table_mut()
+    .and_then(|table| table.remove("include"));
+  let includes = match includes {
+    Some(Value::Array(includes)) => includes,
+    Some(include) => vec![include],
+    None => Vec::new(),
+  };
+  let mut merged = Value::Table(Map::new());
+  for include in includes.iter().filter_map(Value::as_str) {
+    if depth >= 8 {
+      eprintln!("error: includes in {name} are nested too deeply, do they include each other?");
+      exit(1);
+    }
+    let include = expand_path(include, path.parent().unwrap_or(Path::new("/")));
+    if let Some(included) = read_layer(&include, true, depth + 1) {
+      merge_layer(&mut merged, included);
+    }
+  }
+  merge_layer(&mut merged, layer);
 
-                [packages]
+  Some(merged)
+}
 
-                [packages.package_count]
-                enabled = false
+/// Checks every profile of a layer like a config file of its own, so a typo in one is
+/// reported against the file it's written in.
+pub(crate) fn check_profiles(source: &Source, layer: &mut Value) {
+  let Some(Value::Table(profiles)) = layer.get_mut("profiles") else {
+    return;
+  };
+  for (name, profile) in profiles.iter_mut() {
+    validate(source, profile, &["profiles".to_string(), name.clone()]);
+  }
+}
 
-                [packages.update_count]
-                enabled = false
+/// Applies the profile picked with `--profile`, or the one named after the hostname.
+fn apply_profile(merged: &mut Value) {
+  let profiles = merged
+    .as_table_mut()
+    .and_then(|table| table.remove("profiles"));
+  let mut profiles = match profiles {
+    Some(Value::Table(profiles)) => profiles,
+    _ => Map::new(),
+  };
 
-                [song]
-                enabled = false
-                "#,
-      )
-      .unwrap();
+  let name = if ARGS.profile.is_empty() {
+    hostname().unwrap_or_default()
+  } else {
+    ARGS.profile.clone()
+  };
+  match profiles.remove(&name) {
+    Some(profile) => merge(merged, profile),
+    None if !ARGS.profile.is_empty() => {
+      let available: Vec<String> = profiles.keys().cloned().collect();
+      eprintln!(
+        "error: no profile named `{name}`, the config defines: {}",
+        available.join(", ")
+      );
+      exit(1);
     }
-    Err(e) => panic!("{e}"),
-    Ok(content) => match content.as_ref() {
-      "" => {
-        return toml::from_str(
-          r#"
-                [main]
-
-                [util]
+    None => (),
+  }
+}
 
-                [greeting]
-                enabled = true
+/// Parses an override as a TOML value, taking anything that isn't valid TOML as a string
+/// so `--set weather.values.location=Berlin` doesn't need quotes.
+fn parse_value(raw: &str) -> Value {
+  toml::from_str::<Value>(&format!("value = {raw}"))
+    .ok()
+    .and_then(|mut table| table.as_table_mut()?.remove("value"))
+    .unwrap_or_else(|| Value::String(raw.to_string()))
+}
 
-                [icons]
-                enabled = false
+fn set_key(root: &mut Value, path: &[String], value: Value) {
+  let Some((last, tables)) = path.split_last() else {
+    return;
+  };
 
-                [time]
-                enabled = false
+  let mut current = root;
+  for key in tables {
+    let Value::Table(table) = current else {
+      return;
+    };
+    current = table
+      .entry(key.clone())
+      .or_insert_with(|| Value::Table(Map::new()));
+    if !current.is_table() {
+      *current = Value::Table(Map::new());
+    }
+  }
+  if let Value::Table(table) = current {
+    table.insert(last.clone(), value);
+  }
+}
 
-                [weather]
-                enabled = false
+/// Splits an override key on `separator`, keeping the column each key starts at so
+/// diagnostics can point at it.
+fn split_keys(key: &str, separator: &str, start: usize) -> Vec<(String, usize)> {
+  let mut column = start;
+  key
+    .split(separator)
+    .map(|part| {
+      let item = (part.to_lowercase(), column);
+      column += part.len() + separator.len();
+      item
+    })
+    .collect()
+}
 
-                [weather.values]
+/// Keys that are resolved while the config files are read, so overriding them afterwards
+/// would do nothing.
+static FILE_ONLY_KEYS: [&str; 3] = ["include", "profiles", "when"];
+
+/// Lets an override leave out the `values` table of `[weather.values]`, so
+/// `--set weather.location=Berlin` works like `--set weather.values.location=Berlin`.
+fn expand_shorthand(keys: &mut Vec<(String, usize)>) {
+  let shorthand = matches!(
+    keys.as_slice(),
+    [(section, _), (key, _), ..]
+      if section == "weather" && ["api_key", "location", "lang", "units"].contains(&key.as_str())
+  );
+  if shorthand {
+    let column = keys[1].1;
+    keys.insert(1, ("values".to_string(), column));
+  }
+}
 
-                [system]
+/// Turns one override into a layer, checked like a config file so a stale key only
+/// warns and is skipped. `line` is how the override was written.
+fn override_layer(source: &str, line: &str, mut keys: Vec<(String, usize)>, raw: &str) -> Value {
+  expand_shorthand(&mut keys);
+  let path: Vec<String> = keys.iter().map(|(key, _)| key.clone()).collect();
+  let locate = |found: &[String]| {
+    path
+      .starts_with(found)
+      .then(|| (0, keys[found.len() - 1].1))
+  };
+  let source = Source {
+    name: source,
+    content: line,
+    locate: &locate,
+  };
 
-                [system.release]
-                enabled = false
+  let mut layer = Value::Table(Map::new());
+  if FILE_ONLY_KEYS.contains(&path[0].as_str()) {
+    warning(
+      &source,
+      &format!("`{}` can't be overridden", path[0]),
+      &path[..1],
+      "it's only read from config files, this override is ignored",
+    );
+    return layer;
+  }
 
-                [system.kernel]
-                enabled = false
+  set_key(&mut layer, &path, parse_value(raw));
+  validate(&source, &mut layer, &[]);
+  layer
+}
 
-                [system.mem_usage]
-                enabled = false
+/// Reads a `DRACONIS_SECTION__KEY` variable, other `DRACONIS_` variables aren't overrides.
+fn env_override(name: &str, value: &str) -> Option<Value> {
+  let key = name.strip_prefix("DRACONIS_")?;
+  let keys = split_keys(key, "__", "DRACONIS_".len());
+  if keys.len() < 2 || keys.iter().any(|(key, _)| key.is_empty()) {
+    return None;
+  }
 
-                [system.disk_usage]
-                enabled = false
+  Some(override_layer(
+    "environment",
+    &format!("{name}={value}"),
+    keys,
+    value,
+  ))
+}
 
-                [system.desktop_env]
-                enabled = false
+fn set_override(set: &str) -> Value {
+  let Some((key, value)) = set.split_once('=') else {
+    eprintln!("error: --set expects key=value, got `{set}`");
+    exit(2);
+  };
 
-                [packages]
+  let keys = split_keys(key.trim(), ".", "--set ".len());
+  override_layer(
+    "command line",
+    &format!("--set {}={}", key.trim(), value.trim()),
+    keys,
+    value.trim(),
+  )
+}
 
-                [packages.package_count]
-                enabled = false
+/// Collects overrides from `DRACONIS_SECTION__KEY` variables, followed by `--set` flags
+/// so the command line wins.
+fn overrides() -> Vec<Value> {
+  let mut overrides: Vec<Value> = env::vars()
+    .filter_map(|(name, value)| env_override(&name, &value))
+    .collect();
+  overrides.extend(ARGS.set.iter().map(|set| set_override(set)));
 
-                [packages.update_count]
-                enabled = false
+  overrides
+}
 
-                [song]
-                enabled = false
-                "#,
-        )
-        .unwrap();
+pub(crate) fn read_config() -> Config {
+  let mut merged = Value::Table(Map::new());
+  for path in config_paths() {
+    let required = path.as_os_str() == ARGS.config.as_str();
+    if !required {
+      for other in shadowed_configs(&path) {
+        eprintln!(
+          "warning: {} is ignored, only {} is read",
+          other.display(),
+          path.display()
+        );
       }
-      _ => content,
-    },
-  };
-  toml::from_str(&content).unwrap()
+    }
+    if let Some(layer) = read_layer(&path, required, 0) {
+      tracing::debug!("Loaded config layer {}", path.display());
+      merge_layer(&mut merged, layer);
+    }
+  }
+
+  apply_profile(&mut merged);
+  apply_conditions(&mut merged);
+
+  for layer in overrides() {
+    merge(&mut merged, layer);
+  }
+
+  let mut config: Config = merged.try_into().unwrap_or_else(|e| {
+    eprintln!("error: {e}");
+    exit(1);
+  });
+
+  config.compact.enabled |= ARGS.compact;
+  config
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn toml(text: &str) -> Value {
+    toml::from_str(text).unwrap()
+  }
+
+  #[test]
+  fn merge_keeps_keys_from_both_layers() {
+    let mut base = toml("[weather.values]\nlocation = \"Oslo\"\nunits = \"metric\"");
+    merge(&mut base, toml("[weather.values]\nlocation = \"Berlin\""));
+    assert_eq!(
+      base,
+      toml("[weather.values]\nlocation = \"Berlin\"\nunits = \"metric\"")
+    );
+  }
+
+  #[test]
+  fn when_blocks_are_appended_across_files() {
+    let mut base = toml("sections = [{ modules = [\"kernel\"] }]\n[[when]]\nssh = true");
+    merge_layer(
+      &mut base,
+      toml("sections = [{ modules = [\"song\"] }]\n[[when]]\nsession = \"tty\""),
+    );
+    assert_eq!(
+      base,
+      toml(
+        "sections = [{ modules = [\"song\"] }]\n[[when]]\nssh = true\n[[when]]\nsession = \"tty\""
+      )
+    );
+  }
+
+  #[test]
+  fn override_values_are_toml_or_strings() {
+    assert_eq!(parse_value("42"), Value::Integer(42));
+    assert_eq!(parse_value("true"), Value::Boolean(true));
+    assert_eq!(parse_value("Berlin"), Value::String("Berlin".into()));
+    assert_eq!(
+      parse_value("[\"apt\", \"flatpak\"]"),
+      toml("value = [\"apt\", \"flatpak\"]")["value"]
+    );
+  }
+
+  #[test]
+  fn override_keys_keep_their_columns() {
+    assert_eq!(
+      split_keys("WEATHER__VALUES", "__", 9),
+      vec![("weather".to_string(), 9), ("values".to_string(), 18)]
+    );
+  }
+
+  #[test]
+  fn env_overrides_need_a_section_and_key() {
+    assert_eq!(env_override("DRACONIS_DEBUG", "1"), None);
+    assert_eq!(env_override("DRACONIS_WEATHER__", "1"), None);
+    assert_eq!(env_override("PATH", "/bin"), None);
+    assert_eq!(
+      env_override("DRACONIS_WEATHER__VALUES__LOCATION", "Berlin"),
+      Some(toml("[weather.values]\nlocation = \"Berlin\""))
+    );
+  }
+
+  #[test]
+  fn weather_overrides_can_leave_out_values() {
+    let expected = toml("[weather.values]\nlocation = \"Berlin\"");
+    assert_eq!(
+      env_override("DRACONIS_WEATHER__LOCATION", "Berlin"),
+      Some(expected.clone())
+    );
+    assert_eq!(set_override("weather.location=Berlin"), expected);
+    assert_eq!(set_override("weather.values.location=Berlin"), expected);
+  }
+
+  #[test]
+  fn unknown_overrides_are_skipped() {
+    assert_eq!(
+      env_override("DRACONIS_WEATHER__LOCATON", "Berlin"),
+      Some(toml("[weather]"))
+    );
+    assert_eq!(set_override("nope.key=1"), toml(""));
+  }
+
+  #[test]
+  fn file_only_keys_are_not_overridden() {
+    assert_eq!(set_override("include=theme.toml"), toml(""));
+    assert_eq!(
+      env_override("DRACONIS_PROFILES__WORK", "{}"),
+      Some(toml(""))
+    );
+  }
+
+  #[test]
+  fn profiles_are_checked_when_read() {
+    let content = "[profiles.work.weather]\nenabled = true\nlocaton = \"Berlin\"\n";
+    let mut layer = toml(content);
+    let locate = |path: &[String]| crate::util::diagnostics::locate_toml(content, path);
+    let source = Source {
+      name: "config.toml",
+      content,
+      locate: &locate,
+    };
+    check_profiles(&source, &mut layer);
+    assert_eq!(layer, toml("[profiles.work.weather]\nenabled = true"));
+  }
 }
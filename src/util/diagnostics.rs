@@ -0,0 +1,543 @@
+use {
+  crate::util::conf_structs::Config, serde_path_to_error::Segment, std::process::exit, toml::Value,
+};
+
+fn edit_distance(a: &str, b: &str) -> usize {
+  let b: Vec<char> = b.chars().collect();
+  let mut previous: Vec<usize> = (0..=b.len()).collect();
+  for (i, ca) in a.chars().enumerate() {
+    let mut current = vec![i + 1];
+    for (j, cb) in b.iter().enumerate() {
+      let substitution = previous[j] + usize::from(ca != *cb);
+      current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+    }
+    previous = current;
+  }
+
+  previous[b.len()]
+}
+
+fn suggest<'a>(name: &str, candidates: &[&'a str]) -> Option<&'a str> {
+  let limit = (name.len() / 3).max(2);
+  candidates
+    .iter()
+    .map(|candidate| (edit_distance(name, candidate), *candidate))
+    .filter(|(distance, _)| *distance <= limit)
+    .min_by_key(|(distance, _)| *distance)
+    .map(|(_, candidate)| candidate)
+}
+
+fn backticked(text: &str) -> Vec<&str> {
+  text.split('`').skip(1).step_by(2).collect()
+}
+
+fn split_keys(keys: &str) -> Vec<String> {
+  keys
+    .split('.')
+    .map(|key| key.trim().trim_matches('"').to_string())
+    .collect()
+}
+
+/// Inserts the index of the current `[[array]]` table after every key that names one.
+fn with_indices(keys: &[String], arrays: &[(Vec<String>, usize)]) -> Vec<String> {
+  let mut path = Vec::new();
+  for key in keys {
+    path.push(key.clone());
+    if let Some((_, count)) = arrays.iter().find(|(array, _)| *array == path) {
+      path.push((count - 1).to_string());
+    }
+  }
+
+  path
+}
+
+/// Collects the dotted path of every key in the config schema.
+fn schema_paths(
+  root: &serde_json::Value,
+  node: &serde_json::Value,
+  prefix: &str,
+  paths: &mut Vec<String>,
+) {
+  if let Some(name) = node["$ref"]
+    .as_str()
+    .and_then(|r| r.strip_prefix("#/definitions/"))
+  {
+    schema_paths(root, &root["definitions"][name], prefix, paths);
+  }
+  for key in ["allOf", "anyOf", "oneOf"] {
+    for variant in node[key].as_array().into_iter().flatten() {
+      schema_paths(root, variant, prefix, paths);
+    }
+  }
+  for (key, property) in node["properties"].as_object().into_iter().flatten() {
+    let path = if prefix.is_empty() {
+      key.clone()
+    } else {
+      format!("{prefix}.{key}")
+    };
+    schema_paths(root, property, &path, paths);
+    paths.push(path);
+  }
+}
+
+/// Suggests a key somewhere else in the config with a name close to `name`, preferring
+/// one in the same section, for keys written at the wrong depth.
+fn suggest_path(name: &str, section: Option<&String>) -> Option<String> {
+  let schema = serde_json::to_value(schemars::schema_for!(Config)).ok()?;
+  let mut paths = Vec::new();
+  schema_paths(&schema, &schema, "", &mut paths);
+
+  let matching = paths.into_iter().filter(|path| {
+    let last = path.rsplit('.').next().unwrap_or_default();
+    suggest(name, &[last]).is_some()
+  });
+  let (same, other): (Vec<String>, Vec<String>) = matching.partition(|path| {
+    section.is_some_and(|section| path.split('.').next() == Some(section.as_str()))
+  });
+  same.into_iter().chain(other).next()
+}
+
+/// Finds where the key at `path` is written in a TOML file, either as a key (dotted or
+/// not) or as a `[table]` header. Indices in `path` pick the matching `[[array]]` table.
+pub(crate) fn locate_toml(content: &str, path: &[String]) -> Option<(usize, usize)> {
+  let mut current: Vec<String> = Vec::new();
+  let mut arrays: Vec<(Vec<String>, usize)> = Vec::new();
+  let last = path.last()?;
+  for (i, line) in content.lines().enumerate() {
+    let trimmed = line.trim_start();
+    let indent = line.len() - trimmed.len();
+
+    if let Some(header) = trimmed.strip_prefix('[') {
+      let is_array = header.starts_with('[');
+      let header = header.trim_start_matches('[').split(']').next()?;
+      let keys = split_keys(header);
+      let (name, parents) = keys.split_last()?;
+      current = with_indices(parents, &arrays);
+      current.push(name.clone());
+      let matched = current == path;
+      if is_array {
+        let count = match arrays.iter_mut().find(|(array, _)| *array == current) {
+          Some((_, count)) => {
+            *count += 1;
+            *count
+          }
+          None => {
+            arrays.push((current.clone(), 1));
+            1
+          }
+        };
+        current.push((count - 1).to_string());
+      }
+      if matched || current == path {
+        let column = trimmed.find(header)? + header.rfind(name.as_str())?;
+        return Some((i, indent + column));
+      }
+      // A header like `[sistem.kernel]` is the only place `sistem` is written
+      if current.starts_with(path) {
+        let depth = path
+          .iter()
+          .filter(|key| key.parse::<usize>().is_err())
+          .count();
+        let before = keys[..depth.checked_sub(1)?].join(".");
+        let column = trimmed.find(header)? + before.len();
+        let column = column + header.get(before.len()..)?.find(last.as_str())?;
+        return Some((i, indent + column));
+      }
+      continue;
+    }
+
+    let Some((key, _)) = trimmed.split_once('=') else {
+      continue;
+    };
+    let mut full = current.clone();
+    full.extend(split_keys(key));
+    if full.starts_with(path) {
+      let depth = path.len().checked_sub(current.len() + 1)?;
+      let before = split_keys(key)[..depth].join(".");
+      return Some((
+        i,
+        indent + before.len() + key.get(before.len()..)?.find(last.as_str())?,
+      ));
+    }
+  }
+
+  None
+}
+
+/// Reads the key a JSON or YAML line starts with, quoted or not.
+fn mapping_key(line: &str) -> Option<&str> {
+  let (key, rest) = match line.chars().next()? {
+    quote @ ('"' | '\'') => {
+      let (key, rest) = line[1..].split_once(quote)?;
+      (key, rest.trim_start().strip_prefix(':')?)
+    }
+    '#' => return None,
+    _ => line.split_once(':')?,
+  };
+
+  (rest.is_empty() || rest.starts_with(char::is_whitespace)).then_some(key.trim())
+}
+
+/// Finds where the key at `path` is written in a JSON or YAML file by following the
+/// indentation, which is how both are nested when written by hand. List indices in
+/// `path` aren't followed, the first item with the key is taken.
+pub(crate) fn locate_nested(content: &str, path: &[String]) -> Option<(usize, usize)> {
+  let wanted: Vec<&String> = path
+    .iter()
+    .filter(|key| key.parse::<usize>().is_err())
+    .collect();
+  let mut parents: Vec<(usize, &str)> = Vec::new();
+  for (i, line) in content.lines().enumerate() {
+    let mut rest = line.trim_start();
+    while let Some(item) = rest.strip_prefix("- ") {
+      rest = item.trim_start();
+    }
+    let Some(key) = mapping_key(rest) else {
+      continue;
+    };
+
+    let column = line.len() - rest.len();
+    parents.retain(|(indent, _)| *indent < column);
+    parents.push((column, key));
+    if parents
+      .iter()
+      .map(|(_, key)| *key)
+      .eq(wanted.iter().map(|key| key.as_str()))
+    {
+      let quoted = usize::from(rest.starts_with(['"', '\'']));
+      return Some((i, column + quoted));
+    }
+  }
+
+  None
+}
+
+/// Converts a byte offset into a line and column.
+pub(crate) fn line_col(content: &str, offset: usize) -> (usize, usize) {
+  let before = content.get(..offset).unwrap_or(content);
+  let line = before.matches('\n').count();
+  let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1);
+  (line, column)
+}
+
+/// Writes a key path the way it appears in messages, like `sections[1].title`.
+fn dotted(path: &[String]) -> String {
+  let mut text = String::new();
+  for key in path {
+    if key.parse::<usize>().is_ok() {
+      text.push_str(&format!("[{key}]"));
+    } else {
+      if !text.is_empty() {
+        text.push('.');
+      }
+      text.push_str(key);
+    }
+  }
+
+  text
+}
+
+/// Finds the line and column a key path is written at.
+pub(crate) type Locate<'a> = dyn Fn(&[String]) -> Option<(usize, usize)> + 'a;
+
+/// A config file or override being checked, with a way to find where a key path is
+/// written in it.
+pub(crate) struct Source<'a> {
+  pub(crate) name: &'a str,
+  pub(crate) content: &'a str,
+  pub(crate) locate: &'a Locate<'a>,
+}
+
+impl Source<'_> {
+  /// Finds `path`, or the closest key above it that is written in the source, along
+  /// with the width of the key that was found.
+  fn position(&self, path: &[String]) -> Option<(usize, usize, usize)> {
+    (1..=path.len()).rev().find_map(|len| {
+      let (line, column) = (self.locate)(&path[..len])?;
+      Some((line, column, path[len - 1].chars().count()))
+    })
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Severity {
+  Warning,
+  Error,
+}
+
+/// Prints a diagnostic with the offending line underlined, instead of the raw
+/// deserializer message.
+fn emit(
+  severity: Severity,
+  source: &Source,
+  message: &str,
+  position: Option<(usize, usize, usize)>,
+  hint: Option<String>,
+  note: Option<&str>,
+) {
+  let label = match severity {
+    Severity::Warning => "warning",
+    Severity::Error => "error",
+  };
+  eprintln!("{label}: {message}");
+
+  let snippet = position.and_then(|(line, column, width)| {
+    Some((line, column, width, source.content.lines().nth(line)?))
+  });
+  match snippet {
+    Some((line, column, width, text)) => {
+      let number = (line + 1).to_string();
+      let gutter = " ".repeat(number.len());
+      let before = text.get(..column).unwrap_or(text);
+      let token = text.get(column..).unwrap_or_default();
+      let width = if width == 0 {
+        token
+          .find(|c: char| c.is_whitespace() || c == '=' || c == ',')
+          .unwrap_or(token.len())
+          .max(1)
+      } else {
+        width
+      };
+      let prefix: String = before
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+
+      eprintln!("{gutter}--> {}:{}:{}", source.name, line + 1, column + 1);
+      eprintln!("{gutter} |");
+      eprintln!("{number} | {text}");
+      eprintln!(
+        "{gutter} | {prefix}{} {}",
+        "^".repeat(width),
+        hint.unwrap_or_default()
+      );
+      if let Some(note) = note {
+        eprintln!("{gutter} = {note}");
+      }
+    }
+    None => {
+      eprintln!("  --> {}", source.name);
+      for line in hint.iter().map(String::as_str).chain(note) {
+        eprintln!("  = {line}");
+      }
+    }
+  }
+}
+
+/// Reports something in `source` that is skipped, pointing at `path`.
+pub(crate) fn warning(source: &Source, message: &str, path: &[String], note: &str) {
+  emit(
+    Severity::Warning,
+    source,
+    message,
+    source.position(path),
+    None,
+    Some(note),
+  );
+}
+
+/// Reports a file that couldn't be parsed at all and exits.
+pub(crate) fn syntax_error(source: &Source, message: &str, position: Option<(usize, usize)>) -> ! {
+  let message = message.split(" at line ").next().unwrap_or_default();
+  emit(
+    Severity::Error,
+    source,
+    message,
+    position.map(|(line, column)| (line, column, 0)),
+    None,
+    None,
+  );
+  exit(1);
+}
+
+fn remove_key(value: &mut Value, path: &[String]) -> bool {
+  let Some((last, parents)) = path.split_last() else {
+    return false;
+  };
+
+  let mut current = value;
+  for key in parents {
+    let next = match current {
+      Value::Table(table) => table.get_mut(key),
+      Value::Array(items) => key.parse().ok().and_then(|i: usize| items.get_mut(i)),
+      _ => None,
+    };
+    let Some(next) = next else {
+      return false;
+    };
+    current = next;
+  }
+
+  match current {
+    Value::Table(table) => table.remove(last).is_some(),
+    _ => false,
+  }
+}
+
+/// Checks `value` against the config structs. An unknown key is only a warning and is
+/// dropped, so a key renamed by an upgrade doesn't stop the greeter in every new shell,
+/// anything else is an error. `prefix` is where `value` sits in the source.
+pub(crate) fn validate(source: &Source, value: &mut Value, prefix: &[String]) {
+  loop {
+    let Err(error) = serde_path_to_error::deserialize::<_, Config>(value.clone()) else {
+      return;
+    };
+    let relative: Vec<String> = error
+      .path()
+      .iter()
+      .filter_map(|segment| match segment {
+        Segment::Seq { index } => Some(index.to_string()),
+        Segment::Map { key } => Some(key.clone()),
+        Segment::Enum { variant } => Some(variant.clone()),
+        Segment::Unknown => None,
+      })
+      .collect();
+    let path = [prefix, &relative].concat();
+    let text = error.inner().to_string();
+    let message = text.split(" for key `").next().unwrap_or_default();
+    let expected = |rest: &'_ str| -> Vec<String> {
+      backticked(rest.split_once(", expected").map_or("", |(_, e)| e))
+        .into_iter()
+        .map(str::to_string)
+        .collect()
+    };
+
+    if let Some(rest) = message.strip_prefix("unknown field `")
+      && let Some((name, parent)) = path.split_last()
+    {
+      let expected = expected(rest);
+      let expected: Vec<&str> = expected.iter().map(String::as_str).collect();
+      let context = if parent.is_empty() {
+        String::new()
+      } else {
+        format!(" in [{}]", dotted(parent))
+      };
+      emit(
+        Severity::Warning,
+        source,
+        &format!("{message}{context}"),
+        source.position(&path),
+        suggest(name, &expected)
+          .map(str::to_string)
+          .or_else(|| suggest_path(name, relative.first()))
+          .map(|candidate| format!("did you mean `{candidate}`?")),
+        Some("this key is ignored"),
+      );
+      if remove_key(value, &relative) {
+        continue;
+      }
+    }
+
+    let hint = message.strip_prefix("unknown variant `").and_then(|rest| {
+      let name = rest.split('`').next()?;
+      let expected = expected(rest);
+      let expected: Vec<&str> = expected.iter().map(String::as_str).collect();
+      suggest(name, &expected).map(|candidate| format!("did you mean `{candidate}`?"))
+    });
+    let context = if path.is_empty() {
+      String::new()
+    } else {
+      format!(" for `{}`", dotted(&path))
+    };
+    emit(
+      Severity::Error,
+      source,
+      &format!("{message}{context}"),
+      source.position(&path),
+      hint,
+      None,
+    );
+    exit(1);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn keys(path: &[&str]) -> Vec<String> {
+    path.iter().map(|key| key.to_string()).collect()
+  }
+
+  #[test]
+  fn suggests_close_names() {
+    assert_eq!(suggest("titel", &["title", "modules"]), Some("title"));
+    assert_eq!(suggest("colour", &["title", "modules"]), None);
+  }
+
+  #[test]
+  fn suggests_keys_at_another_depth() {
+    assert_eq!(
+      suggest_path("location", Some(&"weather".to_string())).as_deref(),
+      Some("weather.values.location")
+    );
+  }
+
+  #[test]
+  fn locates_keys_in_array_tables() {
+    let content = "[[sections]]\ntitle = \"a\"\n\n[[sections]]\ntitle = \"b\"\n";
+    assert_eq!(
+      locate_toml(content, &keys(&["sections", "1", "title"])),
+      Some((4, 0))
+    );
+    assert_eq!(
+      locate_toml(content, &keys(&["sections", "1"])),
+      Some((3, 2))
+    );
+  }
+
+  #[test]
+  fn locates_dotted_keys() {
+    let content = "[weather]\nvalues.location = \"Berlin\"\n";
+    assert_eq!(
+      locate_toml(content, &keys(&["weather", "values", "location"])),
+      Some((1, 7))
+    );
+  }
+
+  #[test]
+  fn locates_the_start_of_dotted_keys() {
+    let content = "[[when]]\nweathr.enabled = true\n";
+    assert_eq!(
+      locate_toml(content, &keys(&["when", "0", "weathr"])),
+      Some((1, 0))
+    );
+    let content = "weather.values.locaton = \"Berlin\"\n";
+    assert_eq!(
+      locate_toml(content, &keys(&["weather", "values", "locaton"])),
+      Some((0, 15))
+    );
+  }
+
+  #[test]
+  fn locates_tables_only_named_in_a_header() {
+    let content = "[system]\nenabled = true\n\n[sistem.kernel]\nenabled = true\n";
+    assert_eq!(locate_toml(content, &keys(&["sistem"])), Some((3, 1)));
+    let content = "[[sections]]\ntitle = \"a\"\n[sections.modulez.x]\ny = 1\n";
+    assert_eq!(
+      locate_toml(content, &keys(&["sections", "0", "modulez"])),
+      Some((2, 10))
+    );
+  }
+
+  #[test]
+  fn locates_nested_json_and_yaml_keys() {
+    let json = "{\n  \"icons\": {\n    \"kind\": \"emojii\"\n  },\n  \"time\": {\n    \"kind\": \"25h\"\n  }\n}\n";
+    assert_eq!(locate_nested(json, &keys(&["time", "kind"])), Some((5, 5)));
+    let yaml = "icons:\n  kind: emoji\nsections:\n  - title: a\n    modules: [x]\n";
+    assert_eq!(locate_nested(yaml, &keys(&["icons", "kind"])), Some((1, 2)));
+    assert_eq!(
+      locate_nested(yaml, &keys(&["sections", "0", "modules"])),
+      Some((4, 4))
+    );
+    assert_eq!(locate_nested(yaml, &keys(&["kind"])), None);
+  }
+
+  #[test]
+  fn renders_paths_with_indices() {
+    assert_eq!(
+      dotted(&keys(&["sections", "1", "title"])),
+      "sections[1].title"
+    );
+  }
+}
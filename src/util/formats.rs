@@ -0,0 +1,348 @@
+use {
+  crate::util::{
+    conditions::check_conditions,
+    config::check_profiles,
+    diagnostics::{line_col, locate_nested, locate_toml, syntax_error, validate, Source},
+  },
+  kdl::{KdlDocument, KdlNode, KdlValue},
+  std::path::Path,
+  toml::{map::Map, Value},
+};
+
+/// Config file extensions in the order they're looked for.
+pub(crate) static EXTENSIONS: [&str; 5] = ["toml", "yaml", "yml", "json", "kdl"];
+
+/// Turns a JSON value into TOML. TOML has no null, so null keys are dropped, which
+/// means the same to the config as leaving them out.
+fn from_json(value: serde_json::Value) -> Option<Value> {
+  let value = match value {
+    serde_json::Value::Null => return None,
+    serde_json::Value::Bool(value) => Value::Boolean(value),
+    serde_json::Value::Number(number) => match number.as_i64() {
+      Some(number) => Value::Integer(number),
+      None => Value::Float(number.as_f64()?),
+    },
+    serde_json::Value::String(value) => Value::String(value),
+    serde_json::Value::Array(items) => {
+      Value::Array(items.into_iter().filter_map(from_json).collect())
+    }
+    serde_json::Value::Object(map) => Value::Table(
+      map
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, from_json(value)?)))
+        .collect(),
+    ),
+  };
+
+  Some(value)
+}
+
+fn kdl_value(value: &KdlValue) -> Option<Value> {
+  if let Some(value) = value.as_string() {
+    return Some(Value::String(value.to_string()));
+  }
+  if let Some(value) = value.as_bool() {
+    return Some(Value::Boolean(value));
+  }
+  if let Some(value) = value.as_i64() {
+    return Some(Value::Integer(value));
+  }
+  value.as_f64().map(Value::Float)
+}
+
+/// A KDL node that can't be mapped, with the offset of its name.
+type KdlMisuse = (String, usize);
+
+/// The offset each key of a KDL document is written at, by key path.
+type KdlSpans = Vec<(Vec<String>, usize)>;
+
+fn with_key(path: &[String], key: &str) -> Vec<String> {
+  let mut path = path.to_vec();
+  path.push(key.to_string());
+  path
+}
+
+/// Maps a KDL node to a value: a single argument is the value itself and several make a
+/// list, properties and children make a table, and children named `-` are list items.
+/// Arguments next to properties or children would be lost, so they're refused.
+fn kdl_node(node: &KdlNode, path: &[String], spans: &mut KdlSpans) -> Result<Value, KdlMisuse> {
+  let arguments: Vec<Value> = node
+    .entries()
+    .iter()
+    .filter(|entry| entry.name().is_none())
+    .filter_map(|entry| kdl_value(entry.value()))
+    .collect();
+  let mut properties = Map::new();
+  for entry in node.entries() {
+    let Some(name) = entry.name() else {
+      continue;
+    };
+    if let Some(value) = kdl_value(entry.value()) {
+      spans.push((with_key(path, name.value()), name.span().offset()));
+      properties.insert(name.value().to_string(), value);
+    }
+  }
+
+  let name = node.name();
+  if !arguments.is_empty() && (node.children().is_some() || !properties.is_empty()) {
+    let message = format!(
+      "`{}` has arguments as well as properties or children, use one or the other",
+      name.value()
+    );
+    return Err((message, name.span().offset()));
+  }
+
+  let value = match node.children() {
+    Some(children)
+      if !children.nodes().is_empty()
+        && children
+          .nodes()
+          .iter()
+          .all(|child| child.name().value() == "-") =>
+    {
+      let mut items = Vec::new();
+      for (i, child) in children.nodes().iter().enumerate() {
+        let path = with_key(path, &i.to_string());
+        spans.push((path.clone(), child.name().span().offset()));
+        items.push(kdl_node(child, &path, spans)?);
+      }
+      Value::Array(items)
+    }
+    Some(children) => {
+      let mut table = kdl_document(children, path, spans)?;
+      table.extend(properties);
+      Value::Table(table)
+    }
+    None if properties.is_empty() && arguments.len() == 1 => arguments[0].clone(),
+    None if properties.is_empty() && !arguments.is_empty() => Value::Array(arguments),
+    None => Value::Table(properties),
+  };
+
+  Ok(value)
+}
+
+/// Repeating a node makes a list of its values, like `[[sections]]` does in TOML. A
+/// node that isn't repeated is read as a list of one where the config expects a list.
+fn kdl_document(
+  document: &KdlDocument,
+  path: &[String],
+  spans: &mut KdlSpans,
+) -> Result<Map<String, Value>, KdlMisuse> {
+  let count = |name: &str| {
+    document
+      .nodes()
+      .iter()
+      .filter(|node| node.name().value() == name)
+      .count()
+  };
+
+  let mut grouped: Vec<(String, Vec<Value>)> = Vec::new();
+  for node in document.nodes() {
+    let name = node.name().value();
+    let offset = node.name().span().offset();
+    let mut node_path = with_key(path, name);
+    let index = match grouped.iter().position(|(key, _)| key == name) {
+      Some(index) => index,
+      None => {
+        spans.push((node_path.clone(), offset));
+        grouped.push((name.to_string(), Vec::new()));
+        grouped.len() - 1
+      }
+    };
+    let values = &mut grouped[index].1;
+    if count(name) > 1 {
+      node_path.push(values.len().to_string());
+      spans.push((node_path.clone(), offset));
+    }
+    values.push(kdl_node(node, &node_path, spans)?);
+  }
+
+  let table = grouped
+    .into_iter()
+    .map(|(key, mut values)| {
+      let value = if values.len() == 1 {
+        values.remove(0)
+      } else {
+        Value::Array(values)
+      };
+      (key, value)
+    })
+    .collect();
+
+  Ok(table)
+}
+
+/// Parses a config file according to its extension into a TOML value, so every format
+/// is layered the same way. Invalid files are reported against their source and exit,
+/// unknown keys are reported and left out.
+pub(crate) fn parse_config(path: &str, content: &str) -> Value {
+  let extension = Path::new(path)
+    .extension()
+    .and_then(|extension| extension.to_str())
+    .unwrap_or("toml");
+  // Nothing is located before the file is parsed, syntax errors bring their position
+  let unparsed = Source {
+    name: path,
+    content,
+    locate: &|_| None,
+  };
+
+  let mut spans = KdlSpans::new();
+  let mut value = match extension {
+    "json" => {
+      let json: serde_json::Value = serde_json::from_str(content).unwrap_or_else(|e| {
+        let position = (e.line() > 0).then(|| (e.line() - 1, e.column().saturating_sub(1)));
+        syntax_error(&unparsed, &e.to_string(), position)
+      });
+      from_json(json).unwrap_or_else(|| Value::Table(Map::new()))
+    }
+    "yaml" | "yml" => {
+      let yaml: serde_json::Value = serde_yaml::from_str(content).unwrap_or_else(|e| {
+        let position = e
+          .location()
+          .map(|location| (location.line() - 1, location.column() - 1));
+        syntax_error(&unparsed, &e.to_string(), position)
+      });
+      from_json(yaml).unwrap_or_else(|| Value::Table(Map::new()))
+    }
+    "kdl" => {
+      let document: KdlDocument = content.parse().unwrap_or_else(|e: kdl::KdlError| {
+        let position = line_col(content, e.span.offset());
+        syntax_error(&unparsed, &e.to_string(), Some(position))
+      });
+      let table = kdl_document(&document, &[], &mut spans).unwrap_or_else(|(message, offset)| {
+        syntax_error(&unparsed, &message, Some(line_col(content, offset)))
+      });
+      Value::Table(table)
+    }
+    _ => toml::from_str(content)
+      .unwrap_or_else(|e: toml::de::Error| syntax_error(&unparsed, &e.to_string(), e.line_col())),
+  };
+
+  let locate = |key: &[String]| match extension {
+    "kdl" => spans
+      .iter()
+      .find(|(path, _)| path == key)
+      .map(|(_, offset)| line_col(content, *offset)),
+    "json" | "yaml" | "yml" => locate_nested(content, key),
+    _ => locate_toml(content, key),
+  };
+  let source = Source {
+    name: path,
+    content,
+    locate: &locate,
+  };
+  validate(&source, &mut value, &[]);
+  check_profiles(&source, &mut value);
+  check_conditions(&source, &mut value);
+  value
+}
+
+#[cfg(test)]
+mod tests {
+  use {super::*, crate::util::conf_structs::Config};
+
+  fn toml(text: &str) -> Value {
+    toml::from_str(text).unwrap()
+  }
+
+  fn kdl(text: &str) -> Result<Value, KdlMisuse> {
+    kdl_document(&text.parse().unwrap(), &[], &mut Vec::new()).map(Value::Table)
+  }
+
+  #[test]
+  fn json_nulls_are_dropped() {
+    let json = serde_json::json!({
+      "weather": { "enabled": true, "values": { "location": null } },
+      "util": { "width": 60, "ratio": 0.5 },
+      "sections": [{ "title": "a" }, null],
+    });
+    assert_eq!(
+      from_json(json),
+      Some(toml(
+        "sections = [{ title = \"a\" }]\n[weather]\nenabled = true\nvalues = {}\n[util]\nwidth = 60\nratio = 0.5"
+      ))
+    );
+    assert_eq!(from_json(serde_json::Value::Null), None);
+  }
+
+  #[test]
+  fn kdl_nodes_map_to_values() {
+    assert_eq!(
+      kdl("width 60\nmodules \"a\" \"b\"\ngreeting enabled=true\ngradient {\n  - \"red\"\n  - \"blue\"\n}"),
+      Ok(toml(
+        "width = 60\nmodules = [\"a\", \"b\"]\ngreeting = { enabled = true }\ngradient = [\"red\", \"blue\"]"
+      ))
+    );
+  }
+
+  #[test]
+  fn repeated_kdl_nodes_make_a_list() {
+    assert_eq!(
+      kdl("sections {\n  title \"a\"\n}\nsections {\n  title \"b\"\n}"),
+      Ok(toml(
+        "[[sections]]\ntitle = \"a\"\n[[sections]]\ntitle = \"b\""
+      ))
+    );
+    assert_eq!(
+      kdl("sections {\n  title \"a\"\n}"),
+      Ok(toml("[sections]\ntitle = \"a\""))
+    );
+  }
+
+  #[test]
+  fn kdl_arguments_next_to_children_are_refused() {
+    let (message, offset) = kdl("width 1\nicons \"x\" {\n  kind \"emoji\"\n}").unwrap_err();
+    assert!(message.starts_with("`icons` has arguments"));
+    assert_eq!(offset, 8);
+    assert!(kdl("palette \"x\" enabled=true").is_err());
+  }
+
+  #[test]
+  fn kdl_keys_keep_their_offsets() {
+    let text =
+      "sections {\n  title \"a\"\n}\nsections {\n  titel \"b\"\n}\ngreeting enabled=true\n";
+    let mut spans = KdlSpans::new();
+    kdl_document(&text.parse().unwrap(), &[], &mut spans).unwrap();
+    let offset = |path: &[&str]| {
+      let path: Vec<String> = path.iter().map(|key| key.to_string()).collect();
+      spans
+        .iter()
+        .find(|(key, _)| *key == path)
+        .map(|(_, offset)| *offset)
+    };
+    assert_eq!(offset(&["sections"]), Some(0));
+    assert_eq!(offset(&["sections", "1"]), Some(25));
+    assert_eq!(offset(&["sections", "1", "titel"]), Some(38));
+    assert_eq!(offset(&["greeting", "enabled"]), Some(59));
+  }
+
+  #[test]
+  fn every_format_reads_the_same() {
+    let read = |path: &str, content: &str| {
+      let config: Config = parse_config(path, content).try_into().unwrap();
+      Value::try_from(&config).unwrap()
+    };
+    let expected = read(
+      "config.toml",
+      "[icons]\nkind = \"emoji\"\n[[sections]]\ntitle = \"a\"\nmodules = [\"release\"]\n",
+    );
+    let files = [
+      (
+        "config.json",
+        r#"{"icons": {"kind": "emoji"}, "sections": [{"title": "a", "modules": ["release"]}]}"#,
+      ),
+      (
+        "config.yaml",
+        "icons:\n  kind: emoji\nsections:\n  - title: a\n    modules: [release]\n",
+      ),
+      (
+        "config.kdl",
+        "icons {\n  kind \"emoji\"\n}\nsections {\n  title \"a\"\n  modules \"release\"\n}\n",
+      ),
+    ];
+    for (path, content) in files {
+      assert_eq!(read(path, content), expected, "{path}");
+    }
+  }
+}
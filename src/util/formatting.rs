@@ -1,8 +1,16 @@
-use substring::Substring;
-
 use {
-  crate::util::statics::CONF, unicode_segmentation::UnicodeSegmentation,
-  unicode_width::UnicodeWidthStr,
+  crate::{
+    funcs::thresholds::{Alert, Level},
+    util::{
+      conf_structs::{IconKind, LabelPosition, LayoutStyle},
+      markup::{parse_hex, render_markup},
+      statics::{CONF, LEVEL_EMOJIS, LEVEL_ICONS},
+      terminal::{hyperlink, icons_enabled},
+    },
+  },
+  std::collections::HashMap,
+  unicode_segmentation::UnicodeSegmentation,
+  unicode_width::{UnicodeWidthChar, UnicodeWidthStr},
 };
 
 pub(crate) fn upper_first(s: String) -> String {
@@ -13,30 +21,300 @@ pub(crate) fn upper_first(s: String) -> String {
   }
 }
 
+/// A single line of the greeter, before it is decorated and padded.
+pub(crate) struct Row {
+  pub(crate) name: &'static str,
+  pub(crate) icon: &'static str,
+  pub(crate) value: Option<String>,
+  pub(crate) alert: Option<Alert>,
+  pub(crate) link: Option<String>,
+  /// Shorter text used by the compact layout instead of `value`.
+  pub(crate) compact: Option<String>,
+}
+
+impl Row {
+  pub(crate) fn new(name: &'static str, icon: &'static str, value: Option<String>) -> Self {
+    Self {
+      name,
+      icon,
+      value,
+      alert: None,
+      link: None,
+      compact: None,
+    }
+  }
+
+  pub(crate) fn from_pair(name: &'static str, pair: Option<(&'static str, String)>) -> Self {
+    let (icon, value) = pair.map_or(("", None), |(icon, value)| (icon, Some(value)));
+    Self::new(name, icon, value)
+  }
+
+  pub(crate) fn with_alert(mut self, alert: Option<Alert>) -> Self {
+    self.alert = alert;
+    self
+  }
+
+  pub(crate) fn with_link(mut self, link: Option<String>) -> Self {
+    self.link = link;
+    self
+  }
+
+  pub(crate) fn with_compact(mut self, compact: Option<String>) -> Self {
+    self.compact = compact;
+    self
+  }
+}
+
+pub(crate) fn pick_icon(emoji: &'static str, icon: &'static str) -> &'static str {
+  if icons_enabled() {
+    match CONF.icons.kind {
+      Some(IconKind::Emoji) => emoji,
+      Some(IconKind::Normal) => icon,
+      None => "",
+    }
+  } else {
+    ""
+  }
+}
+
+fn level_icon(level: Level) -> &'static str {
+  match level {
+    Level::Critical => pick_icon(LEVEL_EMOJIS[1], LEVEL_ICONS[1]),
+    Level::Warning | Level::Ok => pick_icon(LEVEL_EMOJIS[0], LEVEL_ICONS[0]),
+  }
+}
+
+fn label(name: &str) -> Option<String> {
+  if !CONF.labels.enabled {
+    return None;
+  }
+  label_text(name)
+}
+
+/// The label of a module, whether or not labels are shown in the box.
+pub(crate) fn label_text(name: &str) -> Option<String> {
+  if let Some(text) = CONF.labels.text.get(name) {
+    return Some(render_markup(text, |_| None));
+  }
+
+  let label = match name {
+    "datetime" => "Time",
+    "weather" => "Weather",
+    "release" => "OS",
+    "kernel" => "Kernel",
+    "memory" => "Memory",
+    "disk" => "Disk",
+    "battery" => "Battery",
+    "environment" => "Desktop",
+    "updates" => "Updates",
+    "packages" => "Packages",
+    "song" => "Song",
+    _ => return None,
+  };
+
+  Some(label.to_string())
+}
+
+fn format_row(row: &Row, values: &HashMap<&str, &str>, label_width: usize) -> Option<String> {
+  let value = row.value.as_ref()?;
+  let value = match CONF.format.get(row.name) {
+    Some(template) => render_markup(template, |name| match name {
+      "value" => Some(value.as_str()),
+      name => values.get(name).copied(),
+    }),
+    None => value.clone(),
+  };
+
+  let (icon, value) = match &row.alert {
+    Some(alert) => {
+      let icon = level_icon(alert.level);
+      let value = format!("{}{value}\x1b[0m", alert.level.color());
+      (icon, value)
+    }
+    None => (row.icon, value),
+  };
+  let value = hyperlink(value, row.link.as_deref());
+  let icon = if icon.is_empty() {
+    String::new()
+  } else {
+    format!("{icon} ")
+  };
+  let label = label(row.name)
+    .map(|label| {
+      let padding = label_width.saturating_sub(visible_width(&label));
+      format!("{label}{} ", " ".repeat(padding))
+    })
+    .unwrap_or_default();
+
+  let text = match CONF.labels.position {
+    Some(LabelPosition::After) => format!("│ {icon}{label}{value}"),
+    Some(LabelPosition::Before) | None => format!("│ {label}{icon}{value}"),
+  };
+
+  if row.name == "song" {
+    Some(calc_whitespace_song(text))
+  } else {
+    Some(calc_whitespace(text))
+  }
+}
+
+/// Lists the alerts of the rows that are shown, for printing under the box.
+pub(crate) fn calc_warnings(rows: &[Row]) -> Vec<String> {
+  rows
+    .iter()
+    .filter(|row| row.value.is_some())
+    .filter_map(|row| row.alert.as_ref())
+    .map(|alert| {
+      let icon = match level_icon(alert.level) {
+        "" => "!",
+        icon => icon,
+      };
+      format!("{}{icon} {}\x1b[0m", alert.level.color(), alert.message)
+    })
+    .collect()
+}
+
+fn render_rows(rows: &[Row]) -> Vec<(&'static str, Option<String>)> {
+  let label_width = rows
+    .iter()
+    .filter(|row| row.value.is_some())
+    .filter_map(|row| label(row.name))
+    .map(|label| visible_width(&label))
+    .max()
+    .unwrap_or(0);
+  let values = rows
+    .iter()
+    .filter_map(|row| Some((row.name, row.value.as_deref()?)))
+    .collect();
+
+  rows
+    .iter()
+    .map(|row| (row.name, format_row(row, &values, label_width)))
+    .collect()
+}
+
+/// The modules joined by the compact layout, in order.
+pub(crate) fn compact_modules() -> Vec<&'static str> {
+  match &CONF.compact.modules {
+    Some(order) => order.iter().map(String::as_str).collect(),
+    None => vec![
+      "hostname", "release", "kernel", "memory", "disk", "updates", "song",
+    ],
+  }
+}
+
+pub(crate) fn calc_compact(hostname: Option<&str>, rows: &[Row]) -> String {
+  compact_modules()
+    .iter()
+    .filter_map(|name| {
+      if *name == "hostname" {
+        return hostname;
+      }
+      let row = rows.iter().find(|row| row.name == *name);
+      if row.is_none() {
+        tracing::warn!("Unknown compact module: {}", name);
+      }
+      let row = row?;
+      row.compact.as_deref().or(row.value.as_deref())
+    })
+    .filter(|part| !part.is_empty())
+    .collect::<Vec<_>>()
+    .join(CONF.compact.separator.as_deref().unwrap_or(" • "))
+}
+
+fn gradient_color(ratio: f64) -> Option<String> {
+  let stops: Vec<(u8, u8, u8)> = CONF
+    .bars
+    .gradient
+    .iter()
+    .filter_map(|color| {
+      let rgb = parse_hex(color);
+      if rgb.is_none() {
+        tracing::warn!("Invalid gradient color: {}", color);
+      }
+      rgb
+    })
+    .collect();
+
+  let (r, g, b) = match stops.len() {
+    0 => return None,
+    1 => stops[0],
+    n => {
+      let position = ratio * (n - 1) as f64;
+      let i = (position.floor() as usize).min(n - 2);
+      let t = position - i as f64;
+      let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+      let ((r1, g1, b1), (r2, g2, b2)) = (stops[i], stops[i + 1]);
+      (mix(r1, r2), mix(g1, g2), mix(b1, b2))
+    }
+  };
+
+  Some(format!("\x1b[38;2;{r};{g};{b}m"))
+}
+
+/// Renders `ratio` (0 to 1) as a bar like `[██████░░░░] 62%`.
+pub(crate) fn calc_bar(ratio: f64) -> String {
+  let ratio = ratio.clamp(0.0, 1.0);
+  let length = CONF.bars.length.unwrap_or(10);
+  let filled = (ratio * length as f64).round() as usize;
+  let bar = format!(
+    "{}{}",
+    CONF.bars.filled.as_deref().unwrap_or("█").repeat(filled),
+    CONF
+      .bars
+      .empty
+      .as_deref()
+      .unwrap_or("░")
+      .repeat(length - filled)
+  );
+  let bar = match gradient_color(ratio) {
+    Some(color) => format!("{color}{bar}\x1b[0m"),
+    None => bar,
+  };
+
+  format!("[{bar}] {:.0}%", ratio * 100.0)
+}
+
 pub(crate) fn calc_whitespace(text: String) -> String {
-  let size = ((CONF.util.width - 5) as usize) - text.graphemes(true).count();
-  let fs = format!("{}{}", " ".repeat(size), "│");
+  fit_row(text, (CONF.util.width as usize).saturating_sub(5))
+}
+
+/// Pads `text` to `room` columns and closes the row. Labels and bars can make a row wider
+/// than the box, those are cut with `...` so the border stays in line.
+fn fit_row(text: String, room: usize) -> String {
+  let count = |text: &str| strip_ansi(text).graphemes(true).count();
+  let text = if count(&text) > room {
+    truncate_visible(&text, room.saturating_sub(3))
+  } else {
+    text
+  };
+
+  let fs = format!("{}{}", " ".repeat(room.saturating_sub(count(&text))), "│");
   format!("{text}{fs}")
 }
 
 pub(crate) fn calc_whitespace_song(text: String) -> String {
-  if let (_, false) = ((CONF.util.width).overflowing_sub(3).0 as usize)
-    .overflowing_sub(UnicodeWidthStr::width_cjk(text.as_str()))
-  {
+  let width = UnicodeWidthStr::width_cjk(strip_ansi(&text).as_str());
+  if let (_, false) = ((CONF.util.width).overflowing_sub(3).0 as usize).overflowing_sub(width) {
     let size = ((CONF.util.width).overflowing_sub(3).0 as usize)
-      .overflowing_sub(UnicodeWidthStr::width_cjk(text.as_str()))
+      .overflowing_sub(width)
       .0;
     format!("{text}{}│", " ".repeat(size))
   } else {
-    format!("{}... │", text.substring(0, (CONF.util.width - 9) as usize),)
+    format!(
+      "{} │",
+      truncate_visible(&text, (CONF.util.width - 9) as usize)
+    )
   }
 }
 
 pub(crate) fn calc_with_hostname(text: String) -> String {
-  let size = if CONF.icons.enabled {
-    match CONF.icons.kind.as_deref() {
-      Some("emoji") => ((CONF.util.width + 5) as usize) - text.graphemes(true).count(),
-      Some(&_) | None => ((CONF.util.width + 4) as usize) - text.graphemes(true).count(),
+  let size = if icons_enabled() {
+    match CONF.icons.kind {
+      Some(IconKind::Emoji) => ((CONF.util.width + 5) as usize) - text.graphemes(true).count(),
+      Some(IconKind::Normal) | None => {
+        ((CONF.util.width + 4) as usize) - text.graphemes(true).count()
+      }
     }
   } else {
     ((CONF.util.width + 4) as usize) - text.graphemes(true).count()
@@ -47,10 +325,12 @@ pub(crate) fn calc_with_hostname(text: String) -> String {
 }
 
 pub(crate) fn calc_bottom() -> String {
-  let size = if CONF.icons.enabled {
-    match CONF.icons.kind.as_deref() {
-      Some("emoji") => ((CONF.util.width - 4) as usize) - "╰".graphemes(true).count(),
-      Some(&_) | None => ((CONF.util.width - 5) as usize) - "╰".graphemes(true).count(),
+  let size = if icons_enabled() {
+    match CONF.icons.kind {
+      Some(IconKind::Emoji) => ((CONF.util.width - 4) as usize) - "╰".graphemes(true).count(),
+      Some(IconKind::Normal) | None => {
+        ((CONF.util.width - 5) as usize) - "╰".graphemes(true).count()
+      }
     }
   } else {
     ((CONF.util.width - 5) as usize) - "╰".graphemes(true).count()
@@ -59,3 +339,313 @@ pub(crate) fn calc_bottom() -> String {
   let fs = format!("{}{}", "─".repeat(size), "╯");
   format!("╰{fs}")
 }
+
+fn box_width() -> usize {
+  match CONF.icons.kind {
+    Some(IconKind::Emoji) if icons_enabled() => (CONF.util.width - 3) as usize,
+    _ => (CONF.util.width - 4) as usize,
+  }
+}
+
+pub(crate) fn calc_divider(title: Option<&str>) -> String {
+  match title {
+    Some(title) => {
+      let title = render_markup(title, |_| None);
+      let size = box_width().saturating_sub(7 + visible_width(&title));
+      format!("├─── \x1b[1m{title}\x1b[0m {}┤", "─".repeat(size))
+    }
+    None => format!("├{}┤", "─".repeat(box_width() - 2)),
+  }
+}
+
+fn collect_sections(rows: &[(&str, Option<String>)]) -> Vec<(Option<&'static str>, Vec<String>)> {
+  CONF
+    .sections
+    .iter()
+    .map(|section| {
+      let body: Vec<String> = section
+        .modules
+        .iter()
+        .filter_map(|module| {
+          let row = rows.iter().find(|(name, _)| *name == module.as_str());
+          if row.is_none() {
+            tracing::warn!("Unknown module in section: {}", module);
+          }
+          row
+        })
+        .filter_map(|(_, row)| row.clone())
+        .collect();
+      (
+        section.title.as_deref(),
+        body,
+        section.collapse_empty.unwrap_or(true),
+      )
+    })
+    .filter(|(_, body, collapse)| !(body.is_empty() && *collapse))
+    .map(|(title, body, _)| (title, body))
+    .collect()
+}
+
+fn render_sections(sections: &[(Option<&str>, Vec<String>)]) -> Vec<String> {
+  let mut lines = Vec::new();
+  for (i, (title, body)) in sections.iter().enumerate() {
+    if i > 0 || title.is_some() {
+      lines.push(calc_divider(*title));
+    }
+    lines.extend(body.iter().cloned());
+  }
+
+  lines
+}
+
+fn split_columns(rows: &[(&str, Option<String>)], columns: usize) -> Vec<Vec<String>> {
+  if CONF.sections.is_empty() {
+    let lines: Vec<String> = rows.iter().filter_map(|(_, row)| row.clone()).collect();
+    let per_column = lines.len().div_ceil(columns).max(1);
+    return lines.chunks(per_column).map(<[String]>::to_vec).collect();
+  }
+
+  let sections = collect_sections(rows);
+  let total: usize = sections.iter().map(|(_, body)| body.len() + 1).sum();
+  let target = total.div_ceil(columns);
+
+  let mut split: Vec<Vec<(Option<&str>, Vec<String>)>> = vec![Vec::new()];
+  let mut size = 0;
+  for section in sections {
+    if size >= target && split.len() < columns {
+      split.push(Vec::new());
+      size = 0;
+    }
+    size += section.1.len() + 1;
+    split.last_mut().unwrap().push(section);
+  }
+
+  split.iter().map(|column| render_sections(column)).collect()
+}
+
+/// Splits `text` into CSI and OSC escape sequences and the printed text between them,
+/// the flag is true for escape sequences.
+fn split_ansi(text: &str) -> Vec<(bool, &str)> {
+  let mut parts = Vec::new();
+  let mut start = 0;
+  let mut chars = text.char_indices().peekable();
+  while let Some((i, c)) = chars.next() {
+    if c != '\x1b' {
+      continue;
+    }
+    if start < i {
+      parts.push((false, &text[start..i]));
+    }
+    let end = match chars.next() {
+      // CSI sequences end with a byte in the range @ to ~
+      Some((_, '[')) => chars.find(|(_, c)| ('@'..='~').contains(c)),
+      // OSC sequences end with BEL or ST (ESC \)
+      Some((_, ']')) => loop {
+        match chars.next() {
+          Some((j, '\x07')) => break Some((j, '\x07')),
+          Some((_, '\x1b')) => {
+            if let Some(end) = chars.next_if(|(_, c)| *c == '\\') {
+              break Some(end);
+            }
+          }
+          Some(_) => (),
+          None => break None,
+        }
+      },
+      other => other,
+    };
+    start = end.map_or(text.len(), |(j, c)| j + c.len_utf8());
+    parts.push((true, &text[i..start]));
+  }
+  if start < text.len() {
+    parts.push((false, &text[start..]));
+  }
+
+  parts
+}
+
+/// Removes CSI and OSC escape sequences, leaving only the text that is printed.
+pub(crate) fn strip_ansi(text: &str) -> String {
+  split_ansi(text)
+    .into_iter()
+    .filter(|(escape, _)| !escape)
+    .map(|(_, part)| part)
+    .collect()
+}
+
+/// Cuts the printed text of `text` to `width` columns and marks the cut with `...`.
+/// Every escape sequence is kept, so hyperlinks and colors are still closed after it.
+fn truncate_visible(text: &str, width: usize) -> String {
+  let mut out = String::new();
+  let mut used = 0;
+  let mut cut = false;
+  for (escape, part) in split_ansi(text) {
+    if escape {
+      out.push_str(part);
+      continue;
+    }
+    for c in part.chars() {
+      let c_width = UnicodeWidthChar::width_cjk(c).unwrap_or(0);
+      if !cut && used + c_width > width {
+        out.push_str("...");
+        cut = true;
+      }
+      if !cut {
+        out.push(c);
+        used += c_width;
+      }
+    }
+  }
+  if !cut {
+    out.push_str("...");
+  }
+
+  out
+}
+
+/// Returns the number of terminal columns `text` occupies, ignoring escape sequences.
+pub(crate) fn visible_width(text: &str) -> usize {
+  UnicodeWidthStr::width(strip_ansi(text).as_str())
+}
+
+fn pad_row(line: &str, width: usize) -> String {
+  if !line.starts_with('│') {
+    return line.to_string();
+  }
+  let text = line
+    .strip_suffix('│')
+    .filter(|text| !text.is_empty())
+    .unwrap_or(line)
+    .trim_end();
+  format!(
+    "{text}{}│",
+    " ".repeat(width.saturating_sub(visible_width(text) + 1))
+  )
+}
+
+/// Renders the whole greeter box, laying the rows out in `layout.columns` columns.
+pub(crate) fn render_box(hostname: Option<String>, rows: &[Row]) -> Vec<String> {
+  let rows = render_rows(rows);
+  let top = hostname.map(|hostname| calc_with_hostname(format!("╭─── \x1b[32m{hostname}\x1b[0m ")));
+  let columns = CONF.layout.columns.unwrap_or(1).max(1);
+
+  if columns == 1 {
+    let body = if CONF.sections.is_empty() {
+      rows.iter().filter_map(|(_, row)| row.clone()).collect()
+    } else {
+      render_sections(&collect_sections(&rows))
+    };
+    return top.into_iter().chain(body).chain([calc_bottom()]).collect();
+  }
+
+  let width = box_width();
+  let border = "─".repeat(width - 2);
+  let split = split_columns(&rows, columns);
+  let height = split.iter().map(Vec::len).max().unwrap_or(0);
+
+  if CONF.layout.style == Some(LayoutStyle::Separate) {
+    let boxes: Vec<Vec<String>> = split
+      .iter()
+      .enumerate()
+      .map(|(i, column)| {
+        let top = match &top {
+          Some(top) if i == 0 => top.clone(),
+          _ => format!("╭{border}╮"),
+        };
+        let blank = " ".repeat(width);
+        [top]
+          .into_iter()
+          .chain(column.iter().map(|line| pad_row(line, width)))
+          .chain([calc_bottom()])
+          .chain(std::iter::repeat_n(blank, height - column.len()))
+          .collect()
+      })
+      .collect();
+
+    return (0..height + 2)
+      .map(|i| {
+        boxes
+          .iter()
+          .map(|column| column[i].as_str())
+          .collect::<Vec<_>>()
+          .join(" ")
+      })
+      .collect();
+  }
+
+  let top = top.unwrap_or_else(|| format!("╭{border}╮"));
+  let join = |mut line: String, middle: char, last: char| {
+    line.pop();
+    for _ in 1..split.len() {
+      line.push(middle);
+      line.push_str(&border);
+    }
+    line.push(last);
+    line
+  };
+
+  let mut lines = vec![join(top, '┬', '╮')];
+  for i in 0..height {
+    let mut line = String::new();
+    for column in &split {
+      let cell = column
+        .get(i)
+        .map_or_else(|| pad_row("│", width), |cell| pad_row(cell, width));
+      if line.is_empty() {
+        line = cell;
+        continue;
+      }
+      let mut rest = cell.chars();
+      match (line.pop(), rest.next()) {
+        (Some('┤'), Some('├')) => line.push('┼'),
+        (Some('│'), Some('├')) => line.push('├'),
+        (Some(end), _) => line.push(end),
+        (None, _) => (),
+      }
+      line.push_str(rest.as_str());
+    }
+    lines.push(line);
+  }
+  lines.push(join(calc_bottom(), '┴', '╯'));
+
+  lines
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn strips_csi_and_osc_sequences() {
+    let text = "\x1b[1mbold\x1b[0m \x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\";
+    assert_eq!(strip_ansi(text), "bold link");
+    assert_eq!(strip_ansi("plain"), "plain");
+  }
+
+  #[test]
+  fn truncation_keeps_escape_sequences() {
+    let text = "\x1b]8;;https://example.com\x1b\\\x1b[32mArtist - Song\x1b[0m\x1b]8;;\x1b\\";
+    assert_eq!(
+      truncate_visible(text, 6),
+      "\x1b]8;;https://example.com\x1b\\\x1b[32mArtist...\x1b[0m\x1b]8;;\x1b\\"
+    );
+  }
+
+  #[test]
+  fn labelled_rows_stay_inside_the_box() {
+    let row = "│ Disk \x1b[33m\x1b[0m 244.5 GB Free / 270.6 GB (26.1 GB Used)".to_string();
+    let fitted = fit_row(row, 45);
+    assert_eq!(strip_ansi(&fitted).graphemes(true).count(), 46);
+    assert!(fitted.contains("(26.1 GB..."));
+    assert!(fitted.contains("\x1b[33m\x1b[0m"));
+
+    let short = fit_row("│ Disk  270.6 GB".to_string(), 45);
+    assert_eq!(strip_ansi(&short).graphemes(true).count(), 46);
+    assert!(short.ends_with(" │"));
+  }
+
+  #[test]
+  fn truncation_counts_wide_characters() {
+    assert_eq!(truncate_visible("日本語の歌", 5), "日本...");
+  }
+}
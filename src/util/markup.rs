@@ -0,0 +1,121 @@
+use crate::util::{statics::CAPS, terminal::ColorLevel};
+
+static COLOR_NAMES: [&str; 8] = [
+  "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+pub(crate) fn color_enabled() -> bool {
+  CAPS.color != ColorLevel::None
+}
+
+pub(crate) fn parse_hex(color: &str) -> Option<(u8, u8, u8)> {
+  let hex = color.trim_start_matches('#');
+  if hex.len() != 6 {
+    return None;
+  }
+  Some((
+    u8::from_str_radix(&hex[0..2], 16).ok()?,
+    u8::from_str_radix(&hex[2..4], 16).ok()?,
+    u8::from_str_radix(&hex[4..6], 16).ok()?,
+  ))
+}
+
+/// Translates a color name (`red`, `bright_blue`) or `#rrggbb` into SGR parameters.
+fn color_code(color: &str, background: bool) -> Option<String> {
+  if color.starts_with('#') {
+    let (r, g, b) = parse_hex(color)?;
+    let kind = if background { 48 } else { 38 };
+    return Some(format!("{kind};2;{r};{g};{b}"));
+  }
+
+  let (name, base) = match color.strip_prefix("bright_") {
+    Some(name) => (name, if background { 100 } else { 90 }),
+    None => (color, if background { 40 } else { 30 }),
+  };
+  let index = COLOR_NAMES.iter().position(|known| *known == name)?;
+
+  Some((base + index).to_string())
+}
+
+fn tag(inner: &str) -> Option<String> {
+  let code = match inner {
+    "b" => "1".to_string(),
+    "/b" => "22".to_string(),
+    "i" => "3".to_string(),
+    "/i" => "23".to_string(),
+    "u" => "4".to_string(),
+    "/u" => "24".to_string(),
+    "/fg" => "39".to_string(),
+    "/bg" => "49".to_string(),
+    _ => match inner.split_once('=')? {
+      ("fg", color) => color_code(color, false)?,
+      ("bg", color) => color_code(color, true)?,
+      _ => return None,
+    },
+  };
+
+  if color_enabled() {
+    Some(format!("\x1b[{code}m"))
+  } else {
+    Some(String::new())
+  }
+}
+
+fn placeholder<'a>(inner: &str, lookup: &impl Fn(&str) -> Option<&'a str>) -> Option<String> {
+  let (name, color) = match inner.split_once(':') {
+    Some((name, color)) => (name, Some(color)),
+    None => (inner, None),
+  };
+  let value = lookup(name)?;
+
+  match color {
+    Some(color) if color_enabled() => match color_code(color, false) {
+      Some(code) => Some(format!("\x1b[{code}m{value}\x1b[39m")),
+      None => {
+        tracing::warn!("Unknown color in format string: {}", color);
+        Some(value.to_string())
+      }
+    },
+    _ => Some(value.to_string()),
+  }
+}
+
+/// Renders `<b>`, `<i>`, `<u>`, `<fg=..>` and `<bg=..>` tags as ANSI SGR sequences and
+/// replaces `{name}` or `{name:color}` with the value returned by `lookup`. Anything that
+/// isn't recognised is kept as is.
+pub(crate) fn render_markup<'a>(text: &str, lookup: impl Fn(&str) -> Option<&'a str>) -> String {
+  let mut out = String::new();
+  let mut rest = text;
+
+  while let Some(start) = rest.find(['<', '{']) {
+    out.push_str(&rest[..start]);
+    rest = &rest[start..];
+
+    let is_tag = rest.starts_with('<');
+    let close = if is_tag { '>' } else { '}' };
+    let rendered = rest.find(close).and_then(|end| {
+      let inner = &rest[1..end];
+      let rendered = if is_tag {
+        tag(inner)
+      } else {
+        placeholder(inner, &lookup)
+      };
+      Some((rendered?, end))
+    });
+    match rendered {
+      Some((rendered, end)) => {
+        out.push_str(&rendered);
+        rest = &rest[end + 1..];
+      }
+      // Not a tag or placeholder, like a `<` in a song title, so only that character
+      // is kept as text and the markup after it is still rendered
+      None => {
+        out.push_str(&rest[..1]);
+        rest = &rest[1..];
+      }
+    }
+  }
+  out.push_str(rest);
+
+  out
+}
@@ -1,4 +1,11 @@
+pub mod conditions;
 pub mod conf_structs;
 pub mod config;
+pub mod diagnostics;
+pub mod formats;
 pub mod formatting;
+pub mod markup;
+pub mod render;
+pub mod state;
 pub mod statics;
+pub mod terminal;
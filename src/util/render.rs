@@ -0,0 +1,283 @@
+use {
+  crate::util::{
+    formatting::{label_text, strip_ansi, upper_first, Row},
+    terminal::PALETTE,
+  },
+  resvg::{tiny_skia, usvg},
+  unicode_width::UnicodeWidthStr,
+};
+
+const FOREGROUND: (u8, u8, u8) = (229, 229, 229);
+const BACKGROUND: (u8, u8, u8) = (30, 30, 30);
+const FONT_SIZE: f64 = 14.0;
+const CELL_WIDTH: f64 = FONT_SIZE * 0.6;
+const LINE_HEIGHT: f64 = FONT_SIZE * 1.4;
+const PADDING: f64 = 16.0;
+
+#[derive(Clone, Default, PartialEq)]
+struct Style {
+  fg: Option<(u8, u8, u8)>,
+  bg: Option<(u8, u8, u8)>,
+  bold: bool,
+  italic: bool,
+  underline: bool,
+}
+
+/// A run of text that is printed with the same style.
+struct Span {
+  text: String,
+  style: Style,
+}
+
+fn apply_sgr(style: &mut Style, params: &str) {
+  let params: Vec<u8> = params
+    .split(';')
+    .map(|param| param.parse().unwrap_or(0))
+    .collect();
+  let mut i = 0;
+  while i < params.len() {
+    match params[i] {
+      0 => *style = Style::default(),
+      1 => style.bold = true,
+      22 => style.bold = false,
+      3 => style.italic = true,
+      23 => style.italic = false,
+      4 => style.underline = true,
+      24 => style.underline = false,
+      code @ 30..=37 => style.fg = Some(PALETTE[(code - 30) as usize]),
+      code @ 90..=97 => style.fg = Some(PALETTE[(code - 82) as usize]),
+      39 => style.fg = None,
+      code @ 40..=47 => style.bg = Some(PALETTE[(code - 40) as usize]),
+      code @ 100..=107 => style.bg = Some(PALETTE[(code - 92) as usize]),
+      49 => style.bg = None,
+      kind @ (38 | 48) if params.get(i + 1) == Some(&2) && i + 4 < params.len() => {
+        let rgb = Some((params[i + 2], params[i + 3], params[i + 4]));
+        if kind == 38 {
+          style.fg = rgb;
+        } else {
+          style.bg = rgb;
+        }
+        i += 4;
+      }
+      _ => (),
+    }
+    i += 1;
+  }
+}
+
+/// Splits a line into styled spans, following SGR sequences and dropping any other
+/// escape sequence.
+fn parse_ansi(line: &str) -> Vec<Span> {
+  let mut spans: Vec<Span> = Vec::new();
+  let mut style = Style::default();
+  let mut chars = line.chars().peekable();
+
+  while let Some(c) = chars.next() {
+    if c != '\x1b' {
+      match spans.last_mut() {
+        Some(span) if span.style == style => span.text.push(c),
+        _ => spans.push(Span {
+          text: c.to_string(),
+          style: style.clone(),
+        }),
+      }
+      continue;
+    }
+
+    match chars.next() {
+      Some('[') => {
+        let mut params = String::new();
+        for c in chars.by_ref() {
+          if ('@'..='~').contains(&c) {
+            if c == 'm' {
+              apply_sgr(&mut style, &params);
+            }
+            break;
+          }
+          params.push(c);
+        }
+      }
+      Some(']') => {
+        while let Some(c) = chars.next() {
+          if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+            break;
+          }
+        }
+      }
+      _ => (),
+    }
+  }
+
+  spans
+}
+
+fn hex((r, g, b): (u8, u8, u8)) -> String {
+  format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+fn escape_xml(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
+
+/// Draws the lines as they would appear in a terminal using `font`, placing every span
+/// on the character grid so the box lines up regardless of the font's fallbacks.
+pub(crate) fn render_svg(lines: &[String], font: &str) -> String {
+  let columns = lines
+    .iter()
+    .map(|line| {
+      parse_ansi(line)
+        .iter()
+        .map(|span| UnicodeWidthStr::width(span.text.as_str()))
+        .sum::<usize>()
+    })
+    .max()
+    .unwrap_or(0);
+  let width = (columns as f64 * CELL_WIDTH + PADDING * 2.0).ceil();
+  let height = (lines.len() as f64 * LINE_HEIGHT + PADDING * 2.0).ceil();
+
+  let mut svg = format!(
+    "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+     viewBox=\"0 0 {width} {height}\">\n<rect width=\"100%\" height=\"100%\" rx=\"8\" \
+     fill=\"{}\"/>\n<g font-family=\"{}\" font-size=\"{FONT_SIZE}\" fill=\"{}\" \
+     xml:space=\"preserve\">\n",
+    hex(BACKGROUND),
+    escape_xml(font),
+    hex(FOREGROUND)
+  );
+
+  for (row, line) in lines.iter().enumerate() {
+    let top = PADDING + row as f64 * LINE_HEIGHT;
+    let baseline = top + FONT_SIZE;
+    let mut column = 0;
+
+    for span in parse_ansi(line) {
+      let x = PADDING + column as f64 * CELL_WIDTH;
+      let span_width = UnicodeWidthStr::width(span.text.as_str());
+      column += span_width;
+
+      if let Some(bg) = span.style.bg {
+        svg.push_str(&format!(
+          "<rect x=\"{x}\" y=\"{top}\" width=\"{}\" height=\"{LINE_HEIGHT}\" fill=\"{}\"/>\n",
+          span_width as f64 * CELL_WIDTH,
+          hex(bg)
+        ));
+      }
+      if span.text.trim().is_empty() {
+        continue;
+      }
+
+      let mut attributes = String::new();
+      if let Some(fg) = span.style.fg {
+        attributes.push_str(&format!(" fill=\"{}\"", hex(fg)));
+      }
+      if span.style.bold {
+        attributes.push_str(" font-weight=\"bold\"");
+      }
+      if span.style.italic {
+        attributes.push_str(" font-style=\"italic\"");
+      }
+      if span.style.underline {
+        attributes.push_str(" text-decoration=\"underline\"");
+      }
+      svg.push_str(&format!(
+        "<text x=\"{x}\" y=\"{baseline}\"{attributes}>{}</text>\n",
+        escape_xml(&span.text)
+      ));
+    }
+  }
+  svg.push_str("</g>\n</svg>\n");
+
+  svg
+}
+
+/// Rasterizes an SVG from `render_svg` with the system fonts and writes it as a PNG.
+pub(crate) fn render_png(svg: &str, path: &str) -> Result<(), String> {
+  let mut options = usvg::Options::default();
+  options.fontdb_mut().load_system_fonts();
+
+  let tree = usvg::Tree::from_str(svg, &options).map_err(|e| e.to_string())?;
+  let size = tree.size().to_int_size();
+  let mut pixmap =
+    tiny_skia::Pixmap::new(size.width() * 2, size.height() * 2).ok_or("the image is empty")?;
+  resvg::render(
+    &tree,
+    tiny_skia::Transform::from_scale(2.0, 2.0),
+    &mut pixmap.as_mut(),
+  );
+
+  pixmap.save_png(path).map_err(|e| e.to_string())
+}
+
+/// Writes the lines as a self-contained HTML page, keeping the terminal colors.
+pub(crate) fn render_html(lines: &[String]) -> String {
+  let mut html = format!(
+    "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>draconis</title>\n\
+     </head>\n<body style=\"margin: 0; background: {bg}\">\n<pre style=\"margin: 0; \
+     padding: {PADDING}px; color: {fg}; background: {bg}; font-size: {FONT_SIZE}px; \
+     line-height: 1.4; font-family: 'JetBrainsMono Nerd Font', 'Symbols Nerd Font', \
+     monospace\">",
+    fg = hex(FOREGROUND),
+    bg = hex(BACKGROUND)
+  );
+
+  for line in lines {
+    for span in parse_ansi(line) {
+      let mut style = String::new();
+      if let Some(fg) = span.style.fg {
+        style.push_str(&format!("color: {}; ", hex(fg)));
+      }
+      if let Some(bg) = span.style.bg {
+        style.push_str(&format!("background: {}; ", hex(bg)));
+      }
+      if span.style.bold {
+        style.push_str("font-weight: bold; ");
+      }
+      if span.style.italic {
+        style.push_str("font-style: italic; ");
+      }
+      if span.style.underline {
+        style.push_str("text-decoration: underline; ");
+      }
+
+      let text = escape_xml(&span.text);
+      if style.is_empty() {
+        html.push_str(&text);
+      } else {
+        html.push_str(&format!(
+          "<span style=\"{}\">{text}</span>",
+          style.trim_end()
+        ));
+      }
+    }
+    html.push('\n');
+  }
+  html.push_str("</pre>\n</body>\n</html>\n");
+
+  html
+}
+
+/// Lists the shown modules as a Markdown table of module and value.
+pub(crate) fn render_markdown(hostname: Option<&str>, rows: &[Row]) -> String {
+  let cell = |text: &str| strip_ansi(text).replace('|', "\\|");
+
+  let mut markdown = String::from("| Module | Value |\n| --- | --- |\n");
+  if let Some(hostname) = hostname {
+    markdown.push_str(&format!("| Host | {} |\n", cell(hostname)));
+  }
+  for row in rows {
+    let Some(value) = &row.value else {
+      continue;
+    };
+    if row.name.starts_with("palette") {
+      continue;
+    }
+    let name = label_text(row.name).unwrap_or_else(|| upper_first(row.name.to_string()));
+    markdown.push_str(&format!("| {} | {} |\n", cell(&name), cell(value)));
+  }
+
+  markdown
+}
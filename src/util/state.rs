@@ -0,0 +1,79 @@
+use {
+  crate::util::{conf_structs::ShowPolicy, statics::CONF},
+  chrono::Local,
+  std::{env, fs, path::PathBuf},
+};
+
+fn state_dir() -> Option<PathBuf> {
+  match env::var("XDG_STATE_HOME") {
+    Ok(dir) if !dir.is_empty() => Some(PathBuf::from(dir).join("draconis")),
+    _ => Some(PathBuf::from(env::var("HOME").ok()?).join(".local/state/draconis")),
+  }
+}
+
+fn boot_id() -> Option<String> {
+  Some(
+    fs::read_to_string("/proc/sys/kernel/random/boot_id")
+      .ok()?
+      .trim()
+      .to_string(),
+  )
+}
+
+fn session_id() -> Option<String> {
+  match env::var("XDG_SESSION_ID") {
+    Ok(id) if !id.is_empty() => Some(id),
+    _ => boot_id(),
+  }
+}
+
+/// Decides whether the full greeter should be printed according to `main.show`,
+/// recording the current day, boot and session whenever it is.
+#[tracing::instrument]
+pub(crate) fn should_show_full() -> bool {
+  let key = match CONF.main.show {
+    Some(ShowPolicy::OncePerDay) => "day",
+    Some(ShowPolicy::OncePerBoot) => "boot",
+    Some(ShowPolicy::OncePerSession) => "session",
+    Some(ShowPolicy::Always) | None => return true,
+  };
+
+  let Some(path) = state_dir().map(|dir| dir.join("last_shown")) else {
+    return true;
+  };
+
+  let current = [
+    ("day", Some(Local::now().format("%Y-%m-%d").to_string())),
+    ("boot", boot_id()),
+    ("session", session_id()),
+  ];
+
+  let value = current
+    .iter()
+    .find(|(name, _)| *name == key)
+    .and_then(|(_, value)| value.as_deref());
+  let seen = fs::read_to_string(&path)
+    .unwrap_or_default()
+    .lines()
+    .filter_map(|line| line.split_once('='))
+    .any(|(k, v)| k == key && Some(v) == value);
+
+  if seen {
+    return false;
+  }
+
+  let content: String = current
+    .iter()
+    .filter_map(|(name, value)| Some(format!("{name}={}\n", value.as_ref()?)))
+    .collect();
+
+  if let Err(e) = path
+    .parent()
+    .map_or(Ok(()), fs::create_dir_all)
+    .and_then(|_| fs::write(&path, content))
+  {
+    tracing::warn!("Could not write state file {}: {}", path.display(), e);
+  }
+
+  true
+}
@@ -1,9 +1,15 @@
 use {
-  crate::util::{conf_structs::Config, config::read_config},
+  crate::util::{
+    conf_structs::Config,
+    config::{parse_args, read_config, Args},
+    terminal::{detect_capabilities, Capabilities},
+  },
   once_cell::sync::Lazy,
 };
 
+pub(crate) static ARGS: Lazy<Args> = Lazy::new(parse_args);
 pub(crate) static CONF: Lazy<Config> = Lazy::new(read_config);
+pub(crate) static CAPS: Lazy<Capabilities> = Lazy::new(detect_capabilities);
 
 pub(crate) static GREET_ICONS: [&str; 4] = ["", "", "", ""];
 pub(crate) static GREET_EMOJIS: [&str; 4] = ["🌇", "🏙️", "🌆", "🌃"];
@@ -25,5 +31,7 @@ pub(crate) static PACKAGE_ICONS: [&str; 12] =
 pub(crate) static PACKAGE_EMOJIS: [&str; 13] = [
   "☑️", "1️⃣", "2️⃣", "3️⃣", "4️⃣", "5️⃣", "6️⃣", "7️⃣", "8️⃣", "9️⃣", "🔟", "‼️", "📦",
 ];
-pub(crate) static MISC_ICONS: [&str; 6] = ["", "", "", "", "", ""];
-pub(crate) static MISC_EMOJIS: [&str; 6] = ["💻", "🫀", "🧠", "💾", "🖥️", "🎵"];
+pub(crate) static MISC_ICONS: [&str; 7] = ["", "", "", "", "", "", ""];
+pub(crate) static MISC_EMOJIS: [&str; 7] = ["💻", "🫀", "🧠", "💾", "🖥️", "🎵", "🔋"];
+pub(crate) static LEVEL_ICONS: [&str; 2] = ["", ""];
+pub(crate) static LEVEL_EMOJIS: [&str; 2] = ["⚠️", "🚨"];
@@ -0,0 +1,253 @@
+use {
+  crate::util::{
+    conf_structs::When,
+    statics::{ARGS, CAPS, CONF},
+  },
+  crossterm::tty::IsTty,
+  once_cell::sync::Lazy,
+  regex::{Captures, Regex},
+  std::{env, io::stdout},
+};
+
+static SGR: Lazy<Regex> = Lazy::new(|| Regex::new(r"\x1b\[([0-9;]*)m").unwrap());
+
+pub(crate) static PALETTE: [(u8, u8, u8); 16] = [
+  (0, 0, 0),
+  (205, 0, 0),
+  (0, 205, 0),
+  (205, 205, 0),
+  (0, 0, 238),
+  (205, 0, 205),
+  (0, 205, 205),
+  (229, 229, 229),
+  (127, 127, 127),
+  (255, 0, 0),
+  (0, 255, 0),
+  (255, 255, 0),
+  (92, 92, 255),
+  (255, 0, 255),
+  (0, 255, 255),
+  (255, 255, 255),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum ColorLevel {
+  None,
+  Basic,
+  TrueColor,
+}
+
+#[derive(Debug)]
+pub(crate) struct Capabilities {
+  pub(crate) color: ColorLevel,
+  pub(crate) unicode: bool,
+  pub(crate) hyperlinks: bool,
+}
+
+fn env_is(name: &str, values: &[&str]) -> bool {
+  env::var(name).is_ok_and(|value| {
+    values
+      .iter()
+      .any(|expected| value.eq_ignore_ascii_case(expected))
+  })
+}
+
+/// Whether the terminal can be expected to draw box characters and icons. Only an
+/// explicitly non-UTF-8 locale or a terminal without fonts for them disables this.
+fn unicode_supported() -> bool {
+  if env_is("TERM", &["dumb", "linux"]) {
+    return false;
+  }
+
+  let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+    .iter()
+    .find_map(|name| env::var(name).ok().filter(|value| !value.is_empty()));
+
+  match locale {
+    Some(locale) => {
+      let locale = locale.to_lowercase();
+      locale.contains("utf-8") || locale.contains("utf8")
+    }
+    None => true,
+  }
+}
+
+/// Rendered images and pages don't depend on the terminal they're made in.
+fn targets_terminal() -> bool {
+  ARGS.command != "render" && ARGS.output != "html"
+}
+
+/// Whether icons and box drawing characters can be used in the output. Both the icon
+/// downgrade and the capabilities go by this.
+pub(crate) fn unicode_output() -> bool {
+  !targets_terminal() || unicode_supported()
+}
+
+/// Whether icons are printed. They're dropped while rendering when the output can't show
+/// them, `CONF` keeps what the config says so `config show` prints that.
+pub(crate) fn icons_enabled() -> bool {
+  CONF.icons.enabled && CAPS.unicode
+}
+
+/// OSC 8 support can't be queried, so this goes by the terminals known to implement it.
+fn hyperlinks_supported() -> bool {
+  match CONF.util.hyperlinks {
+    Some(When::Always) => return true,
+    Some(When::Never) => return false,
+    Some(When::Auto) | None => (),
+  }
+
+  if !stdout().is_tty() {
+    return false;
+  }
+
+  env_is(
+    "TERM_PROGRAM",
+    &["iTerm.app", "WezTerm", "vscode", "Hyper", "ghostty"],
+  ) || env_is(
+    "TERM",
+    &[
+      "xterm-kitty",
+      "foot",
+      "alacritty",
+      "wezterm",
+      "xterm-ghostty",
+    ],
+  ) || env::var_os("KITTY_WINDOW_ID").is_some()
+    || env::var_os("WT_SESSION").is_some()
+    || env::var("VTE_VERSION")
+      .ok()
+      .and_then(|version| version.parse::<u32>().ok())
+      .is_some_and(|version| version >= 5000)
+}
+
+#[tracing::instrument]
+pub(crate) fn detect_capabilities() -> Capabilities {
+  if !targets_terminal() {
+    return Capabilities {
+      color: ColorLevel::TrueColor,
+      unicode: true,
+      hyperlinks: false,
+    };
+  }
+
+  let mode = match ARGS.color.as_str() {
+    "" => CONF.util.color.unwrap_or(When::Auto),
+    "always" => When::Always,
+    "never" => When::Never,
+    "auto" => When::Auto,
+    other => {
+      tracing::warn!("Unknown color mode: {}", other);
+      When::Auto
+    }
+  };
+  let truecolor = env_is("COLORTERM", &["truecolor", "24bit"]);
+
+  let color = match mode {
+    When::Never => ColorLevel::None,
+    When::Always if truecolor => ColorLevel::TrueColor,
+    When::Always => ColorLevel::Basic,
+    When::Auto => {
+      if env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+        || env_is("TERM", &["dumb"])
+        || !stdout().is_tty()
+      {
+        ColorLevel::None
+      } else if truecolor {
+        ColorLevel::TrueColor
+      } else {
+        ColorLevel::Basic
+      }
+    }
+  };
+
+  Capabilities {
+    color,
+    unicode: unicode_output(),
+    hyperlinks: hyperlinks_supported(),
+  }
+}
+
+fn nearest_basic((r, g, b): (u8, u8, u8), background: bool) -> String {
+  let distance = |(pr, pg, pb): &(u8, u8, u8)| {
+    let (dr, dg, db) = (
+      r as i32 - *pr as i32,
+      g as i32 - *pg as i32,
+      b as i32 - *pb as i32,
+    );
+    dr * dr + dg * dg + db * db
+  };
+  let index = (0..PALETTE.len())
+    .min_by_key(|i| distance(&PALETTE[*i]))
+    .unwrap_or(7);
+
+  let base = match (index < 8, background) {
+    (true, false) => 30,
+    (true, true) => 40,
+    (false, false) => 82,
+    (false, true) => 92,
+  };
+  (base + index).to_string()
+}
+
+fn downgrade_sgr(params: &str) -> String {
+  let params: Vec<&str> = params.split(';').collect();
+  let mut out = Vec::new();
+  let mut i = 0;
+  while i < params.len() {
+    match (params[i], params.get(i + 1)) {
+      (kind @ ("38" | "48"), Some(&"2")) if i + 4 < params.len() => {
+        let channel = |offset: usize| params[i + offset].parse::<u8>().unwrap_or(0);
+        out.push(nearest_basic(
+          (channel(2), channel(3), channel(4)),
+          kind == "48",
+        ));
+        i += 5;
+      }
+      (param, _) => {
+        out.push(param.to_string());
+        i += 1;
+      }
+    }
+  }
+
+  format!("\x1b[{}m", out.join(";"))
+}
+
+/// Wraps `text` in an OSC 8 hyperlink if the terminal supports them.
+pub(crate) fn hyperlink(text: String, url: Option<&str>) -> String {
+  match url {
+    Some(url) if CAPS.hyperlinks => format!("\x1b]8;;{url}\x1b\\{text}\x1b]8;;\x1b\\"),
+    _ => text,
+  }
+}
+
+fn to_ascii(c: char) -> char {
+  match c {
+    '╭' | '╮' | '╰' | '╯' | '├' | '┤' | '┬' | '┴' | '┼' => '+',
+    '─' => '-',
+    '│' => '|',
+    '█' => '#',
+    '░' => '.',
+    '•' => '*',
+    c => c,
+  }
+}
+
+/// Adapts a line to what the terminal supports before it is printed, dropping or
+/// reducing colors and replacing box drawing characters with ASCII.
+pub(crate) fn adapt(line: &str) -> String {
+  let line = match CAPS.color {
+    ColorLevel::None => SGR.replace_all(line, "").into_owned(),
+    ColorLevel::Basic => SGR
+      .replace_all(line, |caps: &Captures| downgrade_sgr(&caps[1]))
+      .into_owned(),
+    ColorLevel::TrueColor => line.to_string(),
+  };
+
+  if CAPS.unicode {
+    line
+  } else {
+    line.chars().map(to_ascii).collect()
+  }
+}